@@ -0,0 +1,153 @@
+use crate::*;
+use colored::*;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::Mutex;
+
+/// The file name diagnostics give for source passed to `assemble`
+pub const IN_MEMORY_NAME: &str = "<input>";
+
+// the symbol tables are process-wide, so only one `assemble` may use them at a time
+static ASSEMBLER: Mutex<()> = Mutex::new(());
+
+/// Assembles source text without touching the filesystem, for programs that
+/// use basm as a library. The code is placed at `origin` unless it has a
+/// `.start`, and `labels` are known to the source as if it had declared them
+/// at those addresses. Labels, constants and macros do not carry over from
+/// one call to the next. On failure the diagnostics have already been
+/// printed and the number of errors is returned.
+pub fn assemble(
+    source: &str,
+    origin: u16,
+    labels: &BTreeMap<String, u16>,
+) -> Result<Vec<u16>, i32> {
+    let segments = assemble_segments(source, origin, labels)?;
+    Ok(segments
+        .into_iter()
+        .flat_map(|segment| segment.words)
+        .map(|word| word as u16)
+        .collect())
+}
+
+/// Empties every table a previous assembly filled in and puts `.start` back at its default
+pub fn reset_symbol_tables() {
+    V_MAP.lock().unwrap().clear();
+    LABEL_MAP.lock().unwrap().clear();
+    MACRO_MAP.lock().unwrap().clear();
+    IN_MEMORY_SOURCES.lock().unwrap().clear();
+    *START_LOCATION.lock().unwrap() = 100;
+    METADATA_STR.lock().unwrap().clear();
+}
+
+/// Like `assemble`, but keeps the code after each `.org` in its own segment,
+/// as basm writes them to a ROM
+pub fn assemble_segments(
+    source: &str,
+    origin: u16,
+    labels: &BTreeMap<String, u16>,
+) -> Result<Vec<Segment>, i32> {
+    let _guard = ASSEMBLER.lock().unwrap_or_else(|e| e.into_inner());
+    reset_symbol_tables();
+    *START_LOCATION.lock().unwrap() = origin as i64;
+    {
+        let mut l_map = LABEL_MAP.lock().unwrap();
+        for (name, &addr) in labels {
+            l_map.insert(
+                name.to_string(),
+                (IN_MEMORY_NAME.to_string(), 0..0, addr as usize),
+            );
+        }
+    }
+    IN_MEMORY_SOURCES
+        .lock()
+        .unwrap()
+        .insert(IN_MEMORY_NAME.to_string(), source.to_string());
+    let result = assemble_source(source, origin);
+    reset_symbol_tables();
+    result
+}
+
+/// The empty segment a `.org` token opens, or `None` for any other token
+pub fn org_segment(tok: &TokenKind, next: Option<&TokenKind>) -> Option<Segment> {
+    if !matches!(tok, TokenKind::Directive(name) if name.eq_ignore_ascii_case("org")) {
+        return None;
+    }
+    // addresses outside memory were already reported while placing labels
+    let address = next
+        .and_then(directive_address)
+        .and_then(|addr| u16::try_from(addr).ok())
+        .unwrap_or(0);
+    Some(Segment {
+        address,
+        words: Vec::new(),
+    })
+}
+
+/// Prints an error for segments that overlap or run past the end of memory,
+/// returning how many were found
+pub fn report_segment_errors(segments: &[Segment]) -> i32 {
+    let mut error_count = 0;
+    if let Some((first, second)) = find_overlap(segments) {
+        eprintln!(
+            "{}: the segment at {} overlaps the segment at {}, which ends at {}",
+            "error".bright_red(),
+            format!("0x{:04X}", second.address).magenta(),
+            format!("0x{:04X}", first.address).magenta(),
+            format!("0x{:04X}", first.end() - 1).magenta()
+        );
+        error_count += 1;
+    }
+    if let Some(segment) = find_overflow(segments) {
+        eprintln!(
+            "{}: the segment at {} is {} words long and runs past {}",
+            "error".bright_red(),
+            format!("0x{:04X}", segment.address).magenta(),
+            segment.words.len(),
+            "0xFFFF".magenta()
+        );
+        error_count += 1;
+    }
+    error_count
+}
+
+fn assemble_source(source: &str, origin: u16) -> Result<Vec<Segment>, i32> {
+    let mut error_count = 0;
+    let mut parser =
+        create_parser(IN_MEMORY_NAME, source, &mut error_count).ok_or(error_count.max(1))?;
+    let mut toks = parse_tokens(&mut parser, source, &mut error_count).ok_or(error_count.max(1))?;
+    process_includes(&mut toks, &mut error_count);
+    process_macros(&mut toks, &mut error_count);
+    process_start(&mut toks, &mut error_count);
+    if error_count > 0 {
+        return Err(error_count);
+    }
+
+    let toks: Vec<(String, TokenKind, Range<usize>)> = toks
+        .into_iter()
+        .filter(|(_, x, _)| !matches!(x, TokenKind::Newline))
+        .collect();
+    let start = u16::try_from(*START_LOCATION.lock().unwrap()).unwrap_or(origin);
+    let mut segments = vec![Segment {
+        address: start,
+        words: Vec::new(),
+    }];
+    for (ind, (fname, tok, span)) in toks.iter().enumerate() {
+        segments.extend(org_segment(tok, toks.get(ind + 1).map(|(_, next, _)| next)));
+        match encode((fname, tok, span), fname, &toks.get(ind + 1)) {
+            Ok(value) => {
+                if let Some(segment) = segments.last_mut() {
+                    segment.words.extend(value);
+                }
+            }
+            Err((m, _)) => {
+                println!("{m}");
+                error_count += 1;
+            }
+        }
+    }
+    error_count += report_segment_errors(&segments);
+    if error_count > 0 {
+        return Err(error_count);
+    }
+    Ok(segments)
+}
@@ -1,8 +1,17 @@
 use clap::Parser;
 use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub static CONFIG: Lazy<Args> = Lazy::new(declare_config);
 
+/// Whether each stage of assembly is printed. The library reads this rather
+/// than `CONFIG`, which parses the command line, and `main` sets it from `-v`.
+pub static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+pub fn verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
 /// Command line arguments
 #[derive(Parser, Debug)]
 #[command(name = "basm")]
@@ -27,6 +36,10 @@ pub struct Args {
     #[clap(short = 't', long, default_value_t = false, verbatim_doc_comment)]
     pub thin: bool,
 
+    /// Write a symbol file (<output>.sym) for the debugger
+    #[clap(short = 's', long, default_value_t = false)]
+    pub symbols: bool,
+
     /// REPL mode
     #[clap(short = 'r', long, default_value_t = false, verbatim_doc_comment)]
     pub repl: bool,
@@ -42,6 +55,7 @@ pub fn declare_config() -> Args {
         output: Some(output),
         verbose: cli.verbose,
         thin: cli.thin,
+        symbols: cli.symbols,
         repl: cli.repl,
     }
 }
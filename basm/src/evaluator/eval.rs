@@ -102,7 +102,7 @@ pub fn evaluate_expression(
     token_iter: &mut Evalex,
 ) -> Result<i64, ParserError> {
     let expr = parse_expression(file, input, token_iter)?;
-    if CONFIG.verbose {
+    if verbose() {
         print_msg!("BEGINNING AST EXPRESSION EVALUATION\n\nRAW EXPR:\n{expr:?}");
         println!();
         print_msg!("CONSTRUCTED AST");
@@ -1,3 +1,4 @@
+pub mod assembler;
 pub mod codegen;
 pub mod config;
 pub mod err_misc;
@@ -5,9 +6,11 @@ pub mod evaluator;
 pub mod misc;
 pub mod parser;
 pub mod preproc;
+pub mod rom;
 pub mod symbol_table;
 pub mod tokens;
 pub mod validator;
+pub use assembler::*;
 pub use codegen::*;
 pub use config::*;
 pub use err_misc::*;
@@ -15,6 +18,7 @@ pub use evaluator::*;
 pub use misc::*;
 pub use parser::*;
 pub use preproc::*;
+pub use rom::*;
 pub use symbol_table::*;
 pub use tokens::*;
 pub use validator::*;
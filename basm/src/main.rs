@@ -3,9 +3,10 @@ use colored::*;
 use std::fs::File;
 use std::io::{self, Write};
 use std::ops::Range;
-use std::sync::{Arc, Mutex};
+use std::sync::{atomic::Ordering, Arc, Mutex};
 
 fn main() {
+    VERBOSE.store(CONFIG.verbose, Ordering::Relaxed);
     let file = &CONFIG.source.clone().unwrap_or_else(|| "stdin".to_string());
     let fname = &CONFIG.source.clone().unwrap_or_else(|| "stdin".to_string());
 
@@ -214,7 +215,12 @@ fn main() {
         .filter(|(_, x, _)| !matches!(x, TokenKind::Newline))
         .collect();
 
-    let mut binary = Vec::new();
+    let start_bin = *START_LOCATION.lock().unwrap();
+    // code before the first `.org` is loaded at `.start`
+    let mut segments = vec![Segment {
+        address: start_bin as u16,
+        words: Vec::new(),
+    }];
     if toks.is_empty() {
         println!(
             "{}: {} appears empty",
@@ -227,11 +233,19 @@ fn main() {
         );
     }
 
+    let mut lines = Vec::new();
     let vecref = &toks;
     for (ind, (fname, tok, span)) in vecref.iter().enumerate() {
+        segments.extend(org_segment(tok, toks.get(ind + 1).map(|(_, next, _)| next)));
         match encode((fname, tok, span), fname, &toks.get(ind + 1)) {
             Ok(value) => {
-                binary.extend(value);
+                let Some(segment) = segments.last_mut() else {
+                    gen_ice!("NO SEGMENT TO ASSEMBLE INTO");
+                };
+                if matches!(tok, TokenKind::Instruction(_)) {
+                    lines.push((segment.end(), fname.to_string(), span.clone()));
+                }
+                segment.words.extend(value);
             }
             Err((m, similars)) => {
                 println!("{m}");
@@ -265,6 +279,14 @@ fn main() {
         }
     }
 
+    error_count += report_segment_errors(&segments);
+    if CONFIG.thin && segments.iter().filter(|s| !s.words.is_empty()).count() > 1 {
+        eprintln!(
+            "{}: a thin binary has no header to place `.org` segments with",
+            "error".bright_red()
+        );
+        error_count += 1;
+    }
     print_errc!(error_count);
 
     if CONFIG.verbose {
@@ -274,32 +296,15 @@ fn main() {
 
     match &CONFIG.output {
         Some(path) => {
-            let mut bytes: Vec<u8> = Vec::new();
-            for value in &binary {
-                bytes.extend_from_slice(&value.to_be_bytes());
-            }
-
-            let start_bin = *START_LOCATION.lock().unwrap();
-            if !CONFIG.thin {
-                let glob_str = METADATA_STR.lock().unwrap();
-                if glob_str.len() % 2 != 0 {
-                    bytes.insert(0, 0);
-                }
-                for character in glob_str.chars().rev() {
-                    bytes.insert(0, character as u8);
-                }
-                let strlen = if glob_str.len() % 2 == 0 {
-                    glob_str.len()
-                } else {
-                    glob_str.len() + 1
-                };
-                bytes.insert(0, (strlen & 0xff) as u8);
-                bytes.insert(0, ((strlen & 0xff00) >> 8) as u8);
-                bytes.insert(0, (start_bin & 0xff) as u8);
-                bytes.insert(0, ((start_bin & 0xff00) >> 8) as u8);
-                bytes.insert(0, 0x02);
-                bytes.insert(0, 0x01);
-            }
+            let bytes: Vec<u8> = if CONFIG.thin {
+                segments
+                    .iter()
+                    .flat_map(|segment| &segment.words)
+                    .flat_map(|value| value.to_be_bytes())
+                    .collect()
+            } else {
+                build_rom(start_bin as u16, &METADATA_STR.lock().unwrap(), &segments)
+            };
             match write_bytes_to_file(path, &bytes) {
                 Ok(()) => (),
                 Err(e) => {
@@ -308,6 +313,13 @@ fn main() {
                     print_errc!(error_count);
                 }
             }
+            if CONFIG.symbols {
+                if let Err(e) = write_symbol_file(&format!("{path}.sym"), &lines) {
+                    eprintln!("{}: {e}", "error writing symbol file".bright_red());
+                    error_count += 1;
+                    print_errc!(error_count);
+                }
+            }
         }
         _ => {
             gen_ice!("BINARY NAME APPEARS EMPTY - SHOULD BE SET TO `a.out` BY DEFAULT");
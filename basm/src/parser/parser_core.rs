@@ -45,7 +45,7 @@ impl<'a> Parser<'a> {
             errors,
         })
     }
-    pub fn parse(&mut self) -> ParserResult {
+    pub fn parse(&mut self) -> ParserResult<'_> {
         let mut tokens = Vec::new();
 
         while let Some((token, span)) = self.lexer.next() {
@@ -83,7 +83,7 @@ pub fn create_parser<'a>(
     input_string: &'a str,
     error_count: &mut i32,
 ) -> Option<Parser<'a>> {
-    if CONFIG.verbose {
+    if verbose() {
         print_msg!("PARSER CREATION");
     }
     match Parser::new(&String::from(file), input_string) {
@@ -129,7 +129,7 @@ pub fn parse_tokens(
 ) -> Option<Vec<(String, TokenKind, Range<usize>)>> {
     match parser.parse() {
         Ok(tokens) => {
-            if CONFIG.verbose {
+            if verbose() {
                 print_msg!("INITIAL TOKENS (UNEXPANDED MACROS AND DIRECTIVES)");
                 for (_, element, _) in &tokens {
                     println!("{}", element);
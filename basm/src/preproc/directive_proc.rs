@@ -5,7 +5,7 @@ use std::ops::Range;
 pub fn process_start(toks: &mut Vec<(String, TokenKind, Range<usize>)>, error_count: &mut i32) {
     use crate::TokenKind::*;
     let mut toks_iter = toks.clone().into_iter().peekable();
-    let mut start_addr = 100;
+    let mut start_addr = *START_LOCATION.lock().unwrap();
     let mut seen_start = false;
     while let Some((fname, tok, span)) = toks_iter.next() {
         if let Directive(data) = tok {
@@ -69,6 +69,15 @@ pub fn process_start(toks: &mut Vec<(String, TokenKind, Range<usize>)>, error_co
     *toks = new_toks;
 }
 
+/// The address given to `.start` or `.org`, as a literal or a memory address
+pub fn directive_address(tok: &TokenKind) -> Option<i64> {
+    match tok {
+        TokenKind::IntLit(val) => Some(*val),
+        TokenKind::Mem(addr) => addr.data.first().map(|(val, _)| val.get_value()),
+        _ => None,
+    }
+}
+
 fn process_directives(
     toks: &mut [(String, TokenKind, Range<usize>)],
     error_count: &mut i32,
@@ -116,6 +125,31 @@ fn process_directives(
                         toks_iter.next();
                     }
                 }
+                "org" => {
+                    let address = toks_iter.peek().and_then(|(_, t, _)| directive_address(t));
+                    if let Some(addr) = address.filter(|addr| (0..=0xFFFF).contains(addr)) {
+                        loc_counter = addr;
+                        toks_iter.next();
+                    } else if address.is_some() {
+                        handle_core_error(
+                            &fname,
+                            &span,
+                            error_count,
+                            ".org address must be between 0 and 0xFFFF",
+                            None,
+                        );
+                        break;
+                    } else {
+                        handle_core_error(
+                            &fname,
+                            &span,
+                            error_count,
+                            ".org directive must be succeeded by memory address",
+                            None,
+                        );
+                        break;
+                    }
+                }
                 "pad" => {
                     if let Some((_, TokenKind::IntLit(v), _)) = toks_iter.peek() {
                         loc_counter += v;
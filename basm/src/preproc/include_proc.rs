@@ -71,6 +71,9 @@ pub fn process_includes(toks: &mut Vec<(String, TokenKind, Range<usize>)>, error
 }
 
 pub fn read_file(file_path: &str) -> String {
+    if let Some(source) = IN_MEMORY_SOURCES.lock().unwrap().get(file_path) {
+        return source.clone();
+    }
     let mut file_data = match File::open(file_path) {
         Ok(file) => file,
         Err(e) => {
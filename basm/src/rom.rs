@@ -0,0 +1,85 @@
+/// The ROM format version written by this assembler
+pub const ROM_VERSION: u8 = 3;
+
+/// The number of addresses segments can be loaded at
+pub const ADDRESS_SPACE: usize = 0x10000;
+
+/// Words in the header in front of the metadata: the magic and version, the
+/// start address, the metadata length in bytes, the number of segments and
+/// the checksum
+pub const ROM_HEADER_WORDS: usize = 5;
+
+/// A block of words loaded at an address. Each is written to the ROM as its
+/// address and length followed by the words.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Segment {
+    pub address: u16,
+    pub words: Vec<i16>,
+}
+
+impl Segment {
+    /// One past the last address the segment fills
+    pub fn end(&self) -> usize {
+        self.address as usize + self.words.len()
+    }
+}
+
+/// The first pair of segments that fill the same address, if any
+pub fn find_overlap(segments: &[Segment]) -> Option<(&Segment, &Segment)> {
+    let mut sorted: Vec<&Segment> = segments.iter().filter(|s| !s.words.is_empty()).collect();
+    sorted.sort_by_key(|segment| segment.address);
+    sorted
+        .windows(2)
+        .find(|pair| (pair[1].address as usize) < pair[0].end())
+        .map(|pair| (pair[0], pair[1]))
+}
+
+/// The first segment that runs past the last address of memory, if any
+pub fn find_overflow(segments: &[Segment]) -> Option<&Segment> {
+    segments
+        .iter()
+        .find(|segment| segment.end() > ADDRESS_SPACE)
+}
+
+/// Fletcher-16 over the big-endian bytes of the words after the header
+pub fn rom_checksum(words: &[u16]) -> u16 {
+    let (mut low, mut high) = (0u16, 0u16);
+    for byte in words.iter().flat_map(|word| word.to_be_bytes()) {
+        low = (low + byte as u16) % 255;
+        high = (high + low) % 255;
+    }
+    (high << 8) | low
+}
+
+/// Packs metadata text two characters per word, padding an odd length with a zero
+pub fn pack_metadata(metadata: &str) -> Vec<u16> {
+    let bytes: Vec<u8> = metadata.chars().map(|c| c as u8).collect();
+    bytes
+        .chunks(2)
+        .map(|pair| ((pair[0] as u16) << 8) | pair.get(1).copied().unwrap_or(0) as u16)
+        .collect()
+}
+
+/// A complete ROM image: header, metadata and segments. Empty segments are left out.
+pub fn build_rom(start: u16, metadata: &str, segments: &[Segment]) -> Vec<u8> {
+    let segments: Vec<&Segment> = segments.iter().filter(|s| !s.words.is_empty()).collect();
+    let mut body = pack_metadata(metadata);
+    let metadata_len = body.len() * 2;
+    for segment in &segments {
+        body.push(segment.address);
+        body.push(segment.words.len() as u16);
+        body.extend(segment.words.iter().map(|&word| word as u16));
+    }
+    let header = [
+        0x0100 | ROM_VERSION as u16,
+        start,
+        metadata_len as u16,
+        segments.len() as u16,
+        rom_checksum(&body),
+    ];
+    header
+        .iter()
+        .chain(&body)
+        .flat_map(|word| word.to_be_bytes())
+        .collect()
+}
@@ -22,6 +22,9 @@ pub static START_LOCATION: Lazy<Mutex<i64>> = Lazy::new(|| Mutex::new(100));
 
 pub static METADATA_STR: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::from("")));
 
+// name      source text, for sources `assemble` keeps in memory rather than on disk
+pub static IN_MEMORY_SOURCES: SymbolTable<String> = Lazy::new(|| Mutex::new(HashMap::new()));
+
 // - Option<String>: messages about similar entries found (if any)
 // - Vec<(String, Range<usize>)>: (file, place) tuples for similar entries
 pub fn find_similar_entries(input: &str) -> (Option<String>, Vec<(String, Range<usize>)>) {
@@ -102,6 +105,42 @@ pub fn find_similar_entries(input: &str) -> (Option<String>, Vec<(String, Range<
         (Some(messages.join(", ")), results)
     }
 }
+
+/// Writes every label and its address to a symbol file that BDB can load,
+/// followed by the source line of every instruction. `lines` holds each
+/// instruction's address with its file and span.
+pub fn write_symbol_file(
+    path: &str,
+    lines: &[(usize, String, Range<usize>)],
+) -> std::io::Result<()> {
+    use std::io::Write;
+    let l_map = LABEL_MAP.lock().unwrap();
+    let mut labels: Vec<(&String, usize)> = l_map
+        .iter()
+        .map(|(name, (_, _, addr))| (name, *addr))
+        .collect();
+    labels.sort_by_key(|(name, addr)| (*addr, name.to_string()));
+
+    let mut file = std::fs::File::create(path)?;
+    writeln!(file, "; basm symbol file")?;
+    for (name, addr) in labels {
+        writeln!(file, "label {name} 0x{addr:04X}")?;
+    }
+
+    let mut sources: HashMap<&str, String> = HashMap::new();
+    for (addr, fname, span) in lines {
+        let source = sources.entry(fname).or_insert_with(|| read_file(fname));
+        let end = span.start.min(source.len());
+        let line = source.as_bytes()[..end]
+            .iter()
+            .filter(|&&b| b == b'\n')
+            .count()
+            + 1;
+        writeln!(file, "line 0x{addr:04X} {line} {fname}")?;
+    }
+    Ok(())
+}
+
 use prettytable::{row, Table};
 pub fn print_symbol_tables() {
     let v_map = V_MAP.lock().unwrap();
@@ -1,28 +1,146 @@
-#[allow(unused_imports)] // tests
-use clap::CommandFactory;
-use clap::Parser;
+use crate::{parse_address, parse_overlay, render_emu_error, CheckMode, EmuError, Overlay};
+use clap::{
+    error::ErrorKind, parser::ValueSource, ArgMatches, Args, CommandFactory, FromArgMatches,
+    Parser, Subcommand,
+};
+use colored::Colorize;
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{env, fs, path::PathBuf, process};
 
-pub static CONFIG: Lazy<Cli> = Lazy::new(declare_config);
+pub static CONFIG: Lazy<Config> = Lazy::new(declare_config);
 
 #[derive(Parser, Debug)]
 #[command(name = "belle")]
 #[command(version = "0.2.0")]
 #[command(author = "gummi")]
 #[command(about = "BELLE - The Big Endian, Low Level Emulator", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Without a subcommand, `belle <ROM>` is the same as `belle run <ROM>`
+    #[command(flatten)]
+    pub run: RunArgs,
+
+    /// Print the settings in effect after reading belle.toml, then exit
+    #[clap(long, default_value_t = false)]
+    pub print_config: bool,
+
+    // the debugger flags from before the subcommands, so that `belle -d ROM`
+    // and `belle -d --core FILE` still open the debugger
+    #[clap(short = 'd', long, hide = true, conflicts_with_all = RUN_ONLY_ARGS)]
+    pub debug: bool,
+
+    #[clap(long, hide = true, requires = "debug")]
+    pub tui: bool,
+
+    #[clap(long, hide = true, requires = "debug")]
+    pub core: Option<String>,
+}
+
+// the flags of `run` that `debug` does not take
+const RUN_ONLY_ARGS: [&str; 10] = [
+    "verbose",
+    "time_delay",
+    "pretty",
+    "write",
+    "no_print_memory",
+    "no_display",
+    "benchmark",
+    "profile",
+    "profile_out",
+    "coverage",
+];
+
+impl Cli {
+    /// The subcommand given, with `belle ROM` meaning `run` and `belle -d ROM` meaning `debug`
+    pub fn into_command(self) -> Command {
+        match self.command {
+            Some(command) => command,
+            None if self.debug => Command::Debug(DebugArgs {
+                rom: self.run.rom,
+                tui: self.tui,
+                compact_print: self.run.compact_print,
+                core: self.core,
+                checks: self.run.checks,
+                format: self.run.format,
+                guest: self.run.guest,
+            }),
+            None => Command::Run(self.run),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Run a ROM
+    Run(RunArgs),
+
+    /// Step through a ROM, or a crash dump, in the debugger
+    Debug(DebugArgs),
+
+    /// Show a ROM's header, start address, metadata and size
+    Info {
+        /// Path to ROM
+        rom: String,
+
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+
+    /// Print a ROM's code as instructions
+    Disasm {
+        /// Path to ROM
+        rom: String,
+
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+
+    /// Rewrite a ROM of an older format version in the current one
+    UpgradeRom {
+        /// Path to ROM
+        rom: String,
+
+        /// Write the upgraded ROM here instead of over the original
+        #[clap(short = 'o', long, value_name = "FILE")]
+        output: Option<String>,
+
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+
+    /// Run a ROM, printing every instruction as it executes
+    Trace(TraceArgs),
+
+    /// Run the test cases in a manifest and compare their results
+    Test {
+        /// Path to the test manifest
+        manifest: String,
+
+        /// Number of tests to run at once (defaults to the number of CPUs)
+        #[clap(short = 'j', long)]
+        jobs: Option<usize>,
+    },
+}
+
+impl Default for Command {
+    fn default() -> Self {
+        Command::Run(RunArgs::default())
+    }
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct RunArgs {
     /// Path to ROM
-    #[clap(required = true)]
-    pub rom: String,
+    pub rom: Option<String>,
 
     /// Verbose output
     #[clap(short = 'v', long, default_value_t = false)]
     pub verbose: bool,
 
-    /// Enter debugger
-    #[clap(short = 'd', long, default_value_t = false)]
-    pub debug: bool,
-
     /// Clock delay (milliseconds)
     #[clap(short = 't', long)]
     pub time_delay: Option<u32>,
@@ -39,7 +157,7 @@ pub struct Cli {
     #[clap(short = 'n', long, default_value_t = false)]
     pub no_print_memory: bool,
 
-    /// Print CPU state compactly
+    /// Print CPU state compactly (with -v)
     #[clap(short = 'c', long, default_value_t = false)]
     pub compact_print: bool,
 
@@ -50,38 +168,562 @@ pub struct Cli {
     /// Print execution time and cycles
     #[clap(short = 'b', long, default_value_t = false)]
     pub benchmark: bool,
+
+    /// Print a hot-spot report and write collapsed stacks to <ROM>.folded
+    #[clap(long, default_value_t = false)]
+    pub profile: bool,
+
+    /// Write the collapsed stacks to a file instead of <ROM>.folded (implies --profile)
+    #[clap(long, value_name = "FILE")]
+    pub profile_out: Option<String>,
+
+    /// Write LCOV line and branch coverage to a file (needs a `basm -s` symbol file)
+    #[clap(long, value_name = "FILE")]
+    pub coverage: Option<String>,
+
+    #[command(flatten)]
+    pub checks: CheckArgs,
+
+    #[command(flatten)]
+    pub format: FormatArgs,
+
+    #[command(flatten)]
+    pub guest: GuestArgs,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct DebugArgs {
+    /// Path to ROM
+    #[clap(required_unless_present = "core")]
+    pub rom: Option<String>,
+
+    /// Use the full-screen debugger interface
+    #[clap(long, default_value_t = false)]
+    pub tui: bool,
+
+    /// Print CPU state compactly
+    #[clap(short = 'c', long, default_value_t = false)]
+    pub compact_print: bool,
+
+    /// Open a JSON crash dump instead of a ROM
+    #[clap(long, value_name = "FILE")]
+    pub core: Option<String>,
+
+    #[command(flatten)]
+    pub checks: CheckArgs,
+
+    #[command(flatten)]
+    pub format: FormatArgs,
+
+    #[command(flatten)]
+    pub guest: GuestArgs,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct TraceArgs {
+    /// Path to ROM
+    pub rom: String,
+
+    /// Write the trace to a file instead of stdout
+    #[clap(short = 'o', long, value_name = "FILE")]
+    pub output: Option<String>,
+
+    /// Stop after this many instructions
+    #[clap(short = 'l', long)]
+    pub limit: Option<u64>,
+
+    /// Print the registers and flags after every instruction
+    #[clap(short = 'r', long, default_value_t = false)]
+    pub registers: bool,
+
+    #[command(flatten)]
+    pub checks: CheckArgs,
+
+    #[command(flatten)]
+    pub format: FormatArgs,
+
+    #[command(flatten)]
+    pub guest: GuestArgs,
+}
+
+/// Checks on the running program, off by default
+#[derive(Args, Debug, Clone, Default)]
+pub struct CheckArgs {
+    /// Crash on any word that is not an instruction basm could have written
+    #[clap(long, default_value_t = false)]
+    pub strict: bool,
+
+    /// Warn about, or crash on, reads of memory that was never written
+    #[clap(long, value_name = "MODE")]
+    pub uninit: Option<CheckMode>,
+
+    /// Warn about, or crash on, a RET or POP that mixes up return addresses and data
+    #[clap(long, value_name = "MODE")]
+    pub call_stack: Option<CheckMode>,
+}
+
+/// How to read a ROM that is not in the current format
+#[derive(Args, Debug, Clone, Default)]
+pub struct FormatArgs {
+    /// Load a headerless ROM, written by `basm --thin`, at this address
+    #[clap(long, value_name = "ADDR", value_parser = parse_address)]
+    pub thin: Option<u16>,
+}
+
+/// What the program being run is given from outside
+#[derive(Args, Debug, Clone, Default)]
+pub struct GuestArgs {
+    /// Feed INT 9 and INT 40 from a file instead of the terminal
+    #[clap(long, value_name = "FILE", conflicts_with = "input_string")]
+    pub input: Option<String>,
+
+    /// Feed INT 9 and INT 40 from a string instead of the terminal
+    #[clap(long, value_name = "TEXT")]
+    pub input_string: Option<String>,
+
+    /// Copy a raw image into memory at an address after the ROM, e.g. data.bin@0x400
+    #[clap(long, value_name = "FILE@ADDR", value_parser = parse_overlay)]
+    pub load: Vec<Overlay>,
+
+    /// Arguments for the program, placed at the top of memory
+    #[clap(last = true, value_name = "ARGS")]
+    pub args: Vec<String>,
+}
+
+/// The settings of the command being run, after reading `belle.toml`
+#[derive(Debug, Default)]
+pub struct Config {
+    pub command: Command,
+    pub rom: String,
+    pub verbose: bool,
+    pub debug: bool,
+    pub tui: bool,
+    pub time_delay: Option<u32>,
+    pub pretty: bool,
+    pub write: bool,
+    pub no_print_memory: bool,
+    pub compact_print: bool,
+    pub no_display: bool,
+    pub benchmark: bool,
+    pub profile: bool,
+    pub profile_out: Option<String>,
+    pub coverage: Option<String>,
+    pub input: Option<String>,
+    pub input_string: Option<String>,
+    pub args: Vec<String>,
+    pub load: Vec<Overlay>,
+    pub thin: Option<u16>,
+    pub strict: bool,
+    pub uninit: Option<CheckMode>,
+    pub call_stack: Option<CheckMode>,
+    pub core: Option<String>,
+    pub print_config: bool,
+    /// The configuration files that were read, user-level first
+    pub config_files: Vec<String>,
+}
+
+impl Config {
+    pub fn new(command: Command, print_config: bool) -> Self {
+        let mut config = Config {
+            print_config,
+            ..Config::default()
+        };
+        let mut guest = |guest: &GuestArgs| {
+            config.input = guest.input.clone();
+            config.input_string = guest.input_string.clone();
+            config.args = guest.args.clone();
+            config.load = guest.load.clone();
+        };
+        match &command {
+            Command::Run(run) => {
+                guest(&run.guest);
+                config.thin = run.format.thin;
+                config.strict = run.checks.strict;
+                config.uninit = run.checks.uninit;
+                config.call_stack = run.checks.call_stack;
+                config.rom = run.rom.clone().unwrap_or_default();
+                config.verbose = run.verbose;
+                config.time_delay = run.time_delay;
+                config.pretty = run.pretty;
+                config.write = run.write;
+                config.no_print_memory = run.no_print_memory;
+                config.compact_print = run.compact_print;
+                config.no_display = run.no_display;
+                config.benchmark = run.benchmark;
+                config.profile = run.profile;
+                config.profile_out = run.profile_out.clone();
+                config.coverage = run.coverage.clone();
+            }
+            Command::Debug(debug) => {
+                guest(&debug.guest);
+                config.thin = debug.format.thin;
+                config.strict = debug.checks.strict;
+                config.uninit = debug.checks.uninit;
+                config.call_stack = debug.checks.call_stack;
+                config.rom = debug.rom.clone().unwrap_or_default();
+                config.debug = true;
+                config.tui = debug.tui;
+                config.compact_print = debug.compact_print;
+                config.core = debug.core.clone();
+            }
+            Command::Trace(trace) => {
+                guest(&trace.guest);
+                config.thin = trace.format.thin;
+                config.strict = trace.checks.strict;
+                config.uninit = trace.checks.uninit;
+                config.call_stack = trace.checks.call_stack;
+                config.rom = trace.rom.clone();
+                config.no_display = true;
+            }
+            Command::Info { rom, format }
+            | Command::Disasm { rom, format }
+            | Command::UpgradeRom { rom, format, .. } => {
+                config.rom = rom.clone();
+                config.thin = format.thin;
+            }
+            Command::Test { .. } => {}
+        }
+        config.command = command;
+        config
+    }
+
+    /// `-c` only changes what verbose output and the debugger print, so given on
+    /// the command line it needs one of them. From belle.toml it is left alone,
+    /// as the file applies to runs with and without `-v`.
+    pub fn check_compact_print(&self, from_cli: bool) -> Result<(), EmuError> {
+        if from_cli && !(self.debug || self.verbose) {
+            return Err(EmuError::Impossible(
+                "Must enable debug or verbose flags with compact print".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Where the profiler writes collapsed stacks, or `None` when not profiling
+    pub fn profile_output(&self) -> Option<String> {
+        match &self.profile_out {
+            Some(path) => Some(path.clone()),
+            None => self.profile.then(|| format!("{}.folded", self.rom)),
+        }
+    }
 }
+
 #[allow(unreachable_code)]
-pub fn declare_config() -> Cli {
+pub fn declare_config() -> Config {
     #[cfg(test)]
     {
-        return Cli {
-            rom: "".to_string(),
-            debug: false,
-            verbose: false,
-            time_delay: None,
-            pretty: false,
-            write: false,
-            no_print_memory: false,
-            compact_print: false,
+        return Config {
             no_display: true,
-            benchmark: false,
+            ..Config::default()
         };
     }
     #[cfg(fuzzing)]
     {
-        return Cli {
-            rom: "".to_string(),
-            debug: false,
-            verbose: false,
-            time_delay: None,
-            pretty: false,
-            write: false,
+        return Config {
             no_print_memory: true,
-            compact_print: false,
             no_display: true,
-            benchmark: false,
+            ..Config::default()
         };
     }
-    Cli::parse()
+    let definition = Cli::command();
+    let matches = definition.clone().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    // settings from files only apply to flags the command actually has
+    let (name, matches) = match matches.subcommand() {
+        Some((name, sub_matches)) => (name, sub_matches),
+        None if cli.debug => ("debug", &matches),
+        None => ("run", &matches),
+    };
+    let accepted = accepted_args(&definition, name);
+
+    let print_config = cli.print_config;
+    let command = cli.into_command();
+    // the ROM is optional on the top level so that `belle --print-config` works
+    let no_rom = matches!(
+        &command,
+        Command::Run(RunArgs { rom: None, .. })
+            | Command::Debug(DebugArgs {
+                rom: None,
+                core: None,
+                ..
+            })
+    );
+    if no_rom && !print_config {
+        Cli::command()
+            .error(ErrorKind::MissingRequiredArgument, "no ROM provided")
+            .exit();
+    }
+    let mut config = Config::new(command, print_config);
+    let compact_print_flag = config.compact_print;
+    for path in config_file_paths() {
+        if !path.is_file() {
+            continue;
+        }
+        let path = path.to_string_lossy().to_string();
+        match FileConfig::load(&path) {
+            Ok(file) => file.apply(&mut config, matches, &accepted),
+            Err(e) => {
+                eprintln!("[{}]: {e}", "EMULATOR ERROR".red());
+                process::exit(1);
+            }
+        }
+        config.config_files.push(path);
+    }
+    if let Err(e) = config.check_compact_print(compact_print_flag) {
+        if !config.print_config {
+            eprintln!("{}", render_emu_error(&e));
+            process::exit(1);
+        }
+    }
+    config
+}
+
+// the ids of the arguments a subcommand takes
+fn accepted_args(definition: &clap::Command, subcommand: &str) -> Vec<String> {
+    definition
+        .find_subcommand(subcommand)
+        .map(|command| {
+            command
+                .get_arguments()
+                .map(|arg| arg.get_id().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Defaults read from `belle.toml`. Each key is named after the flag it sets.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub verbose: Option<bool>,
+    pub tui: Option<bool>,
+    pub time_delay: Option<u32>,
+    pub pretty: Option<bool>,
+    pub write: Option<bool>,
+    pub no_print_memory: Option<bool>,
+    pub compact_print: Option<bool>,
+    pub no_display: Option<bool>,
+    pub benchmark: Option<bool>,
+    pub profile: Option<bool>,
+    pub profile_out: Option<String>,
+    pub coverage: Option<String>,
+    pub input: Option<String>,
+    pub input_string: Option<String>,
+    pub strict: Option<bool>,
+    pub uninit: Option<CheckMode>,
+    pub call_stack: Option<CheckMode>,
+}
+
+impl FileConfig {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("cannot read {path}: {e}"))?;
+        toml::from_str(&text).map_err(|e| format!("invalid configuration in {path}: {e}"))
+    }
+
+    // sets every field the command accepts that was not given on the command line
+    fn apply(&self, config: &mut Config, matches: &ArgMatches, accepted: &[String]) {
+        let accepts = |id: &str| accepted.iter().any(|a| a == id);
+        let from_cli =
+            |id: &str| accepts(id) && matches.value_source(id) == Some(ValueSource::CommandLine);
+        macro_rules! apply {
+            ($($field:ident),*; $($optional:ident),*) => {
+                $(if let Some(value) = &self.$field {
+                    if accepts(stringify!($field)) && !from_cli(stringify!($field)) {
+                        config.$field = value.clone();
+                    }
+                })*
+                $(if let Some(value) = &self.$optional {
+                    if accepts(stringify!($optional)) && !from_cli(stringify!($optional)) {
+                        config.$optional = Some(value.clone());
+                    }
+                })*
+            };
+        }
+        apply!(
+            verbose, tui, pretty, write, no_print_memory, compact_print, no_display, benchmark,
+            profile, strict; time_delay, profile_out, coverage, input, input_string,
+            uninit, call_stack
+        );
+        // the two ways of giving input replace each other
+        if from_cli("input") && !from_cli("input_string") {
+            config.input_string = None;
+        }
+        if from_cli("input_string") && !from_cli("input") {
+            config.input = None;
+        }
+    }
+
+    /// The settings in effect, as they would be written in `belle.toml`
+    pub fn effective(config: &Config) -> Self {
+        Self {
+            verbose: Some(config.verbose),
+            tui: Some(config.tui),
+            time_delay: config.time_delay,
+            pretty: Some(config.pretty),
+            write: Some(config.write),
+            no_print_memory: Some(config.no_print_memory),
+            compact_print: Some(config.compact_print),
+            no_display: Some(config.no_display),
+            benchmark: Some(config.benchmark),
+            profile: Some(config.profile),
+            profile_out: config.profile_out.clone(),
+            coverage: config.coverage.clone(),
+            input: config.input.clone(),
+            input_string: config.input_string.clone(),
+            strict: Some(config.strict),
+            uninit: config.uninit,
+            call_stack: config.call_stack,
+        }
+    }
+}
+
+/// The user-level configuration followed by the one in the working directory,
+/// so that a project's settings win
+pub fn config_file_paths() -> Vec<PathBuf> {
+    let var = |name: &str| env::var_os(name).filter(|value| !value.is_empty());
+    let user_dir = var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("APPDATA").map(PathBuf::from))
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".config")));
+    let mut paths = Vec::new();
+    if let Some(dir) = user_dir {
+        paths.push(dir.join("belle").join("belle.toml"));
+    }
+    paths.push(PathBuf::from("belle.toml"));
+    paths
+}
+
+pub fn print_config(config: &Config) {
+    if config.config_files.is_empty() {
+        println!("# no configuration files found");
+    }
+    for path in &config.config_files {
+        println!("# read {path}");
+    }
+    match toml::to_string(&FileConfig::effective(config)) {
+        Ok(text) => print!("{text}"),
+        Err(e) => eprintln!("[{}]: {e}", "EMULATOR ERROR".red()),
+    }
+}
+
+#[test]
+fn command_line_overrides_config_file() {
+    let matches = Cli::command()
+        .try_get_matches_from(["belle", "rom.bin", "-t", "3", "--input", "in.txt"])
+        .unwrap();
+    let cli = Cli::from_arg_matches(&matches).unwrap();
+    let accepted = accepted_args(&Cli::command(), "run");
+    let mut config = Config::new(Command::Run(cli.run), false);
+    let file: FileConfig =
+        toml::from_str("time_delay = 9\nverbose = true\ninput_string = \"5\"\ntui = true\n")
+            .unwrap();
+    file.apply(&mut config, &matches, &accepted);
+    assert_eq!(config.rom, "rom.bin");
+    assert_eq!(config.time_delay, Some(3));
+    assert!(config.verbose);
+    // only the debugger has a full-screen interface
+    assert!(!config.tui);
+    assert_eq!(config.input.as_deref(), Some("in.txt"));
+    assert_eq!(config.input_string, None);
+}
+
+#[test]
+fn subcommands() {
+    let parse = |args: &[&str]| Cli::try_parse_from(args).map(|cli| cli.command);
+    assert!(matches!(parse(&["belle", "rom.bin"]), Ok(None)));
+    assert!(matches!(
+        parse(&["belle", "run", "rom.bin", "-v"]),
+        Ok(Some(Command::Run(RunArgs { verbose: true, .. })))
+    ));
+    assert!(matches!(
+        parse(&["belle", "debug", "--core", "crashdump-00.json"]),
+        Ok(Some(Command::Debug(DebugArgs { rom: None, .. })))
+    ));
+    assert!(matches!(
+        parse(&["belle", "trace", "rom.bin", "-l", "10", "--", "a"]),
+        Ok(Some(Command::Trace(TraceArgs {
+            limit: Some(10),
+            ..
+        })))
+    ));
+    assert!(parse(&["belle", "debug", "rom.bin", "-v"]).is_err());
+}
+
+#[test]
+fn debugger_flags_without_subcommand() {
+    let parse = |args: &[&str]| Cli::try_parse_from(args).map(Cli::into_command);
+    assert!(matches!(
+        parse(&["belle", "-d", "--tui", "rom.bin", "-c"]),
+        Ok(Command::Debug(DebugArgs {
+            rom: Some(_),
+            tui: true,
+            compact_print: true,
+            core: None,
+            ..
+        }))
+    ));
+    assert!(matches!(
+        parse(&["belle", "--debug", "--core", "crashdump-00.json"]),
+        Ok(Command::Debug(DebugArgs {
+            rom: None,
+            core: Some(_),
+            ..
+        }))
+    ));
+    assert!(matches!(
+        parse(&["belle", "rom.bin"]),
+        Ok(Command::Run(RunArgs { rom: Some(_), .. }))
+    ));
+    // the debugger flags only make sense together, and not with run-only ones
+    assert!(parse(&["belle", "--tui", "rom.bin"]).is_err());
+    assert!(parse(&["belle", "-d", "-v", "rom.bin"]).is_err());
+    // settings from files apply as they would to `belle debug`
+    assert!(accepted_args(&Cli::command(), "debug").contains(&"tui".to_string()));
+    assert!(!accepted_args(&Cli::command(), "run").contains(&"tui".to_string()));
+}
+
+#[test]
+fn profile_output_path() {
+    let run = |args: &[&str]| {
+        let cli = Cli::try_parse_from(args).unwrap();
+        Config::new(Command::Run(cli.run), false)
+    };
+    assert_eq!(
+        run(&["belle", "rom.bin", "--profile"])
+            .profile_output()
+            .as_deref(),
+        Some("rom.bin.folded")
+    );
+    assert_eq!(
+        run(&["belle", "rom.bin", "--profile-out", "p.folded"])
+            .profile_output()
+            .as_deref(),
+        Some("p.folded")
+    );
+    assert_eq!(run(&["belle", "rom.bin"]).profile_output(), None);
+}
+
+#[test]
+fn compact_print_from_file() {
+    let matches = Cli::command()
+        .try_get_matches_from(["belle", "rom.bin"])
+        .unwrap();
+    let cli = Cli::from_arg_matches(&matches).unwrap();
+    let mut config = Config::new(Command::Run(cli.run), false);
+    let file: FileConfig = toml::from_str("compact_print = true\n").unwrap();
+    file.apply(
+        &mut config,
+        &matches,
+        &accepted_args(&Cli::command(), "run"),
+    );
+    assert!(config.compact_print);
+    assert!(config.check_compact_print(false).is_ok());
+
+    // only -c on the command line needs -v
+    let cli = Cli::try_parse_from(["belle", "rom.bin", "-c"]).unwrap();
+    let config = Config::new(Command::Run(cli.run), false);
+    assert!(config.check_compact_print(config.compact_print).is_err());
+    let cli = Cli::try_parse_from(["belle", "rom.bin", "-c", "-v"]).unwrap();
+    let config = Config::new(Command::Run(cli.run), false);
+    assert!(config.check_compact_print(config.compact_print).is_ok());
 }
@@ -0,0 +1,129 @@
+use crate::{config::CONFIG, *};
+use colored::Colorize;
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Write},
+};
+
+/// Records which addresses executed and which way each conditional branch went
+pub struct Coverage {
+    hits: Box<[u64; MEMORY_SIZE]>,
+    // times taken and not taken, per conditional branch
+    branches: BTreeMap<u16, (u64, u64)>,
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self {
+            hits: Box::new([0; MEMORY_SIZE]),
+            branches: BTreeMap::new(),
+        }
+    }
+
+    /// Records one executed instruction. `pc` is from before it ran.
+    pub fn record(&mut self, pc: u16, ins: &Instruction, cpu: &CPU) {
+        self.hits[pc as usize] += 1;
+        if is_conditional(ins) {
+            let (taken, not_taken) = self.branches.entry(pc).or_default();
+            if cpu.pc == pc.wrapping_add(1) {
+                *not_taken += 1;
+            } else {
+                *taken += 1;
+            }
+        }
+    }
+
+    /// Writes LCOV records for every source file that has instructions in `symbols`
+    pub fn write_lcov(
+        &self,
+        out: &mut impl Write,
+        symbols: &SymbolTable,
+        memory: &[u16],
+    ) -> io::Result<()> {
+        // file -> line -> addresses of the instructions on it, which can be
+        // more than one when a macro body is expanded several times
+        let mut files: BTreeMap<&str, BTreeMap<usize, Vec<u16>>> = BTreeMap::new();
+        for (addr, source) in &symbols.lines {
+            files
+                .entry(&source.file)
+                .or_default()
+                .entry(source.line)
+                .or_default()
+                .push(*addr);
+        }
+
+        for (file, lines) in files {
+            writeln!(out, "TN:")?;
+            writeln!(out, "SF:{file}")?;
+            let (mut branches_found, mut branches_hit) = (0, 0);
+            for (line, addrs) in &lines {
+                for (block, addr) in addrs.iter().enumerate() {
+                    if !is_conditional(&CPU::decode_word(memory[*addr as usize])) {
+                        continue;
+                    }
+                    let (taken, not_taken) = self.branches.get(addr).copied().unwrap_or_default();
+                    let executed = self.hits[*addr as usize] > 0;
+                    for (branch, count) in [taken, not_taken].into_iter().enumerate() {
+                        branches_found += 1;
+                        if count > 0 {
+                            branches_hit += 1;
+                        }
+                        // '-' marks a branch whose instruction never ran
+                        let count = if executed {
+                            count.to_string()
+                        } else {
+                            "-".to_string()
+                        };
+                        writeln!(out, "BRDA:{line},{block},{branch},{count}")?;
+                    }
+                }
+            }
+            writeln!(out, "BRF:{branches_found}")?;
+            writeln!(out, "BRH:{branches_hit}")?;
+
+            let mut lines_hit = 0;
+            for (line, addrs) in &lines {
+                let hits: u64 = addrs.iter().map(|&a| self.hits[a as usize]).sum();
+                if hits > 0 {
+                    lines_hit += 1;
+                }
+                writeln!(out, "DA:{line},{hits}")?;
+            }
+            writeln!(out, "LF:{}", lines.len())?;
+            writeln!(out, "LH:{lines_hit}")?;
+            writeln!(out, "end_of_record")?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(&self, cpu: &CPU) {
+        let Some(path) = &CONFIG.coverage else {
+            return;
+        };
+        let symbols = SymbolTable::for_rom(&CONFIG.rom);
+        if symbols.lines.is_empty() {
+            eprintln!(
+                "{}: no line information in {}.sym, assemble with `basm -s` to record coverage",
+                "warning".yellow(),
+                CONFIG.rom
+            );
+            return;
+        }
+        let result = File::create(path)
+            .and_then(|mut file| self.write_lcov(&mut file, &symbols, &cpu.memory[..]));
+        if let Err(e) = result {
+            eprintln!("Failed to write coverage to {path}: {e}");
+        }
+    }
+}
+
+fn is_conditional(ins: &Instruction) -> bool {
+    ins.is_jump() && !matches!(ins, Instruction::JMP(_))
+}
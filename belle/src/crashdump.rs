@@ -1,60 +1,262 @@
-use crate::*;
+use crate::{config::CONFIG, *};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
 use std::{
+    collections::VecDeque,
     fs::{self, File},
-    io::Write,
+    io,
 };
-pub fn write_crash(cpu: &CPU) {
-    let mut file_index = 0;
-    let mut filename;
 
-    loop {
-        filename = format!("crashdump-{file_index:02}.txt");
-        if fs::metadata(&filename).is_err() {
-            let mut file = File::create(&filename).expect("Failed to create file");
+pub const CRASH_DUMP_VERSION: u32 = 1;
 
-            let mut write_to_file = |msg: &str| {
-                if let Err(e) = writeln!(file, "{}", msg) {
-                    eprintln!("Failed to write to file: {}", e);
-                }
-            };
-
-            write_to_file("------ CRASH DUMP ------");
-            write_to_file(&format!("\n\n  Integer Registers : {:?}", cpu.int_reg));
-            write_to_file(&format!("  Float Registers          : {:?}", cpu.float_reg));
-            write_to_file(&format!("  Program Counter          : {}", cpu.pc));
-            write_to_file(&format!("  Instruction Register     : {:016b}", cpu.ir));
-            write_to_file(&format!("  Running                  : {}", cpu.running));
-            write_to_file(&format!("  Zero flag                : {}", cpu.zflag));
-            write_to_file(&format!("  Overflow flag            : {}", cpu.oflag));
-            write_to_file(&format!("  Remainder flag           : {}", cpu.rflag));
-            write_to_file(&format!("  Sign flag                : {}", cpu.sflag));
-            write_to_file(&format!("  Stack pointer            : {}", cpu.sp));
-            write_to_file(&format!("  Base pointer             : {}", cpu.bp));
-            write_to_file(&format!(
-                "  Disassembled Instruction : {}",
-                cpu.decode_instruction()
-            ));
+/// Everything needed to rebuild a crashed CPU in the debugger
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CrashDump {
+    pub version: u32,
+    pub rom: String,
+    pub error: Option<CrashError>,
+    pub registers: CrashRegisters,
+    /// Oldest first, ending with the instruction that crashed
+    pub history: Vec<CrashHistoryEntry>,
+    /// Return addresses found on the stack, innermost first
+    #[serde(default)]
+    pub backtrace: Vec<CrashFrame>,
+    /// Runs of non-zero memory
+    pub memory: Vec<MemorySegment>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CrashError {
+    pub kind: String,
+    pub message: Option<String>,
+    pub address: u16,
+    #[serde(default)]
+    pub symbol: Option<String>,
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CrashFrame {
+    pub address: u16,
+    pub symbol: Option<String>,
+    pub source: Option<String>,
+}
 
-            write_to_file("\n------ MEMORY ------\n");
-            for (index, value) in cpu.memory.iter().enumerate() {
-                if cpu.memory[index] != 0 {
-                    if index == cpu.pc as usize {
-                        write_to_file(&format!(
-                            "Address {index:4X}: {:016b}: {} <---- CRASH OCCURRED HERE",
-                            value,
-                            cpu.decode_instruction()
-                        ));
-                    } else {
-                        write_to_file(&format!(
-                            "Address {index:4X}: {:016b}: {}",
-                            value,
-                            cpu.decode_instruction()
-                        ));
-                    }
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CrashRegisters {
+    pub int: [u16; 6],
+    pub float: [f32; 2],
+    pub pc: u16,
+    pub ir: i16,
+    pub sp: u16,
+    pub bp: u16,
+    pub starts_at: u16,
+    pub zflag: bool,
+    pub oflag: bool,
+    pub rflag: bool,
+    pub sflag: bool,
+    pub pushret: bool,
+    pub backward_stack: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CrashHistoryEntry {
+    pub address: u16,
+    pub word: u16,
+    pub instruction: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemorySegment {
+    pub start: u16,
+    pub words: Vec<u16>,
+}
+
+impl CrashFrame {
+    pub fn describe(&self) -> String {
+        describe_address(self.address, self.symbol.as_deref(), self.source.as_deref())
+    }
+}
+
+impl CrashError {
+    pub fn describe(&self) -> String {
+        describe_address(self.address, self.symbol.as_deref(), self.source.as_deref())
+    }
+}
+
+impl CrashDump {
+    pub fn new(cpu: &CPU, rom: &str, error: Option<&UnrecoverableError>) -> Self {
+        let symbols = SymbolTable::for_rom(rom);
+        let mut memory: Vec<MemorySegment> = Vec::new();
+        for (addr, &word) in cpu.memory.iter().enumerate() {
+            if word == 0 {
+                continue;
+            }
+            match memory.last_mut() {
+                Some(segment) if segment.start as usize + segment.words.len() == addr => {
+                    segment.words.push(word)
                 }
+                _ => memory.push(MemorySegment {
+                    start: addr as u16,
+                    words: vec![word],
+                }),
             }
-            break;
+        }
+
+        Self {
+            version: CRASH_DUMP_VERSION,
+            rom: rom.to_string(),
+            error: error.map(|e| CrashError {
+                kind: e.kind().to_string(),
+                message: e.message().map(str::to_string),
+                address: e.location(),
+                symbol: symbols.symbolize(e.location()),
+                source: symbols.source(e.location()).map(|s| s.to_string()),
+            }),
+            registers: CrashRegisters {
+                int: cpu.int_reg,
+                float: cpu.float_reg,
+                pc: cpu.pc,
+                ir: cpu.ir,
+                sp: cpu.sp,
+                bp: cpu.bp,
+                starts_at: cpu.starts_at,
+                zflag: cpu.zflag,
+                oflag: cpu.oflag,
+                rflag: cpu.rflag,
+                sflag: cpu.sflag,
+                pushret: cpu.pushret,
+                backward_stack: cpu.backward_stack,
+            },
+            history: cpu
+                .history
+                .iter()
+                .map(|&(address, word)| CrashHistoryEntry {
+                    address,
+                    word,
+                    instruction: CPU::decode_word(word).to_string(),
+                })
+                .collect(),
+            backtrace: backtrace(cpu)
+                .into_iter()
+                .map(|address| CrashFrame {
+                    address,
+                    symbol: symbols.symbolize(address),
+                    source: symbols.source(address).map(|s| s.to_string()),
+                })
+                .collect(),
+            memory,
+        }
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        let dump: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if dump.version != CRASH_DUMP_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "crash dump version {} is not supported (expected {CRASH_DUMP_VERSION})",
+                    dump.version
+                ),
+            ));
+        }
+        Ok(dump)
+    }
+
+    /// Rebuilds the CPU as it was when the dump was written
+    pub fn to_cpu(&self) -> CPU {
+        let mut cpu = CPU::new();
+        let regs = &self.registers;
+        cpu.int_reg = regs.int;
+        cpu.float_reg = regs.float;
+        cpu.pc = regs.pc;
+        cpu.ir = regs.ir;
+        cpu.sp = regs.sp;
+        cpu.bp = regs.bp;
+        cpu.starts_at = regs.starts_at;
+        cpu.zflag = regs.zflag;
+        cpu.oflag = regs.oflag;
+        cpu.rflag = regs.rflag;
+        cpu.sflag = regs.sflag;
+        cpu.pushret = regs.pushret;
+        cpu.backward_stack = regs.backward_stack;
+        for segment in &self.memory {
+            let start = segment.start as usize;
+            let end = (start + segment.words.len()).min(MEMORY_SIZE);
+            cpu.memory[start..end].copy_from_slice(&segment.words[..end - start]);
+        }
+        cpu.history = self
+            .history
+            .iter()
+            .map(|entry| (entry.address, entry.word))
+            .collect::<VecDeque<_>>();
+        if let Some(error) = &self.error {
+            cpu.err = true;
+            cpu.errmsg = error.kind.clone();
+        }
+        cpu
+    }
+}
+
+/// Writes the CPU to the first free `crashdump-NN.json`, returning its name
+pub fn write_crash(cpu: &CPU, rom: &str, error: Option<&UnrecoverableError>) -> io::Result<String> {
+    let mut file_index = 0;
+    let filename = loop {
+        let filename = format!("crashdump-{file_index:02}.json");
+        if fs::metadata(&filename).is_err() {
+            break filename;
         }
         file_index += 1;
+    };
+    let file = File::create(&filename)?;
+    serde_json::to_writer_pretty(file, &CrashDump::new(cpu, rom, error))?;
+    Ok(filename)
+}
+
+/// The return addresses on the stack, innermost first. A slot counts as one
+/// when it points into the program at a jump or branch, since that is what
+/// gets pushed while pushret is set.
+pub fn backtrace(cpu: &CPU) -> Vec<u16> {
+    let slots: Vec<usize> = if cpu.sp > cpu.bp || cpu.backward_stack {
+        (cpu.bp as usize + 1..=cpu.sp as usize).rev().collect()
+    } else {
+        (cpu.sp as usize..cpu.bp as usize).collect()
+    };
+    slots
+        .into_iter()
+        .filter_map(|slot| cpu.memory.get(slot).copied())
+        .filter(|&addr| {
+            addr >= cpu.starts_at && CPU::decode_word(cpu.memory[addr as usize]).is_jump()
+        })
+        .collect()
+}
+
+/// Prints where a crash happened and how the program got there
+pub fn print_crash_report(cpu: &CPU, symbols: &SymbolTable, error: &UnrecoverableError) {
+    // the error itself only shows its message in verbose mode
+    if !(CONFIG.debug || CONFIG.verbose) {
+        if let Some(message) = error.message() {
+            eprintln!("{}", message.yellow());
+        }
+    }
+    eprintln!(
+        "{} {}",
+        "at".bold(),
+        symbols.describe(error.location()).magenta()
+    );
+    let frames = backtrace(cpu);
+    if frames.is_empty() {
+        return;
+    }
+    eprintln!("{}", "Backtrace:".bold());
+    for (depth, addr) in frames.into_iter().enumerate() {
+        eprintln!(
+            "  #{depth} {} {}",
+            symbols.describe(addr).magenta(),
+            CPU::decode_word(cpu.memory[addr as usize])
+                .to_string()
+                .green()
+        );
     }
 }
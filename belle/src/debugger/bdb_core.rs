@@ -1,7 +1,12 @@
 use crate::{CPU, *};
 use colored::Colorize;
 use std::{
+    collections::BTreeMap,
     io::{self, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     vec::Vec,
 };
 pub fn cls() {
@@ -11,29 +16,70 @@ pub struct BDB {
     pub dbgcpu: CPU,
     pub breakpoints: Vec<u16>,
     pub exe: String,
+    pub patches: Vec<Patch>,
+    pub displays: Vec<String>,
+    pub symbols: SymbolTable,
+    pub snapshots: BTreeMap<String, CPU>,
+    pub lockstep: Option<Lockstep>,
+    pub executing: Arc<AtomicBool>, // set while 'r' is running the program
+    pub interrupted: Arc<AtomicBool>, // set by Ctrl-C to pause a running program
 }
 
 impl BDB {
     pub fn new(executable_path: &str) -> io::Result<Self> {
-        let mut dbgcpu = CPU::new();
-        dbgcpu.debugging = true;
         Ok(Self {
-            dbgcpu,
+            dbgcpu: Self::new_cpu(),
             exe: executable_path.to_string(),
             breakpoints: Vec::new(),
+            patches: Vec::new(),
+            displays: Vec::new(),
+            symbols: SymbolTable::for_rom(executable_path),
+            snapshots: BTreeMap::new(),
+            lockstep: None,
+            executing: Arc::new(AtomicBool::new(false)),
+            interrupted: Arc::new(AtomicBool::new(false)),
         })
     }
 
+    /// Opens a crash dump written by `belle -w` for inspection
+    pub fn from_core(path: &str) -> io::Result<Self> {
+        let dump = CrashDump::load(path)?;
+        let mut bdb = Self::new(&dump.rom)?;
+        bdb.dbgcpu = dump.to_cpu();
+        bdb.dbgcpu.debugging = true;
+        bdb.dbgcpu.warning_handler = Some(print_warning);
+        match &dump.error {
+            Some(error) => println!(
+                "Loaded crash dump of '{}': {} at {}{}",
+                dump.rom,
+                error.kind.red().bold(),
+                error.describe().magenta(),
+                error
+                    .message
+                    .as_ref()
+                    .map(|m| format!("\n{}", m.yellow()))
+                    .unwrap_or_default()
+            ),
+            None => println!("Loaded crash dump of '{}'.", dump.rom),
+        }
+        if !dump.backtrace.is_empty() {
+            println!("{}", "Backtrace:".bold());
+            for (depth, frame) in dump.backtrace.iter().enumerate() {
+                println!("  #{depth} {}", frame.describe().magenta());
+            }
+        }
+        println!("Type 'history' to see the instructions that led up to it.");
+        Ok(bdb)
+    }
+
     pub fn run(&mut self) -> io::Result<()> {
         let prompt = "(bdb)> ".green();
         println!("Welcome to the BELLE-debugger!");
         println!("First time? Type 'h' or 'help'");
 
+        self.install_interrupt_handler();
+
         loop {
-            let _ = ctrlc::set_handler(move || {
-                println!("\nExiting...");
-                std::process::exit(0);
-            });
             print!("\n{prompt}");
             let mut input = String::new();
             io::stdout().flush()?;
@@ -54,7 +100,7 @@ impl BDB {
                 "h" | "help" => Self::handle_help(arg),
                 "l" => {
                     if let Err(e) = self.dbgcpu.load_rom(&create_rom(&self.exe)?) {
-                        eprintln!("{e}");
+                        eprintln!("{}", render_emu_error(&e));
                         return Ok(());
                     }
                 }
@@ -62,11 +108,12 @@ impl BDB {
                 "spc" => self.handle_set_pc(arg),
                 "p" | "pmem" => self.handle_print_memory(arg),
                 "wb" => self.handle_where_begins(),
-                "e" | "exc" => self.handle_execute(),
+                "e" | "exc" => self.handle_execute(arg),
                 "a" => self.handle_print_all_memory(),
                 "w" => self.handle_print_cpu_state(),
                 "cls" | "clear" => self.cls(),
                 "pk" => self.handle_poke(arg),
+                "set" => self.handle_set(arg),
                 "b" => self.handle_set_breakpoint(arg),
                 "br" => self.handle_remove_breakpoint(arg),
                 "bp" => self.handle_print_all_breakpoints(),
@@ -75,23 +122,57 @@ impl BDB {
                     self.breakpoints.clear();
                 }
                 "rs" => self.reset_cpu(),
+                "disas" => self.handle_disassemble(arg),
+                "asm" => self.handle_assemble(arg),
+                "patches" => self.handle_patches(arg),
+                "find" => self.handle_find(arg),
+                "display" => self.handle_display(arg),
+                "undisplay" => self.handle_undisplay(arg),
+                "until" | "u" => self.handle_until(arg),
+                "animate" => self.handle_animate(arg),
+                "snap" | "snapshot" => self.handle_snapshot(arg),
+                "diff" => self.handle_diff(arg),
+                "lockstep" => self.handle_lockstep(arg),
+                "history" => self.handle_history(),
+                "bt" | "backtrace" => self.handle_backtrace(),
+                c if c == "x" || c.starts_with("x/") => self.handle_examine(c, arg),
                 _ => Self::unknown_command(command),
             }
         }
     }
 
-    fn parse_command(input: &str) -> (&str, &str) {
+    pub fn install_interrupt_handler(&self) {
+        let executing = Arc::clone(&self.executing);
+        let interrupted = Arc::clone(&self.interrupted);
+        let _ = ctrlc::set_handler(move || {
+            if executing.load(Ordering::SeqCst) {
+                interrupted.store(true, Ordering::SeqCst);
+            } else {
+                println!("\nExiting...");
+                std::process::exit(0);
+            }
+        });
+    }
+
+    pub fn parse_command(input: &str) -> (&str, &str) {
         let mut parts = input.splitn(2, ' ');
         (parts.next().unwrap(), parts.next().unwrap_or(""))
     }
 
-    fn cls(&self) {
+    pub(crate) fn cls(&self) {
         print!("\x1B[2J\x1B[1;1H");
     }
 
+    /// A CPU as the debugger runs it, printing its warnings
+    pub(crate) fn new_cpu() -> CPU {
+        let mut cpu = CPU::new();
+        cpu.debugging = true;
+        cpu.warning_handler = Some(print_warning);
+        cpu
+    }
+
     fn reset_cpu(&mut self) {
-        self.dbgcpu = CPU::new();
-        self.dbgcpu.debugging = true;
+        self.dbgcpu = Self::new_cpu();
         println!("CPU reset.");
     }
 
@@ -0,0 +1,175 @@
+use crate::bdb_utils::parse_number;
+use crate::*;
+use colored::*;
+use std::{sync::atomic::Ordering, thread, time::Duration};
+
+const ANIMATE_DEFAULT_DELAY: u64 = 250;
+const DISPLAY_STRING_MAX: usize = 80;
+
+impl BDB {
+    // display, or display <expr>
+    pub fn handle_display(&mut self, arg: &str) {
+        let expr = arg.trim();
+        if expr.is_empty() {
+            if self.displays.is_empty() {
+                println!("No displays.");
+            }
+            self.print_displays();
+            return;
+        }
+        match self.evaluate_display(expr) {
+            Ok(value) => {
+                self.displays.push(expr.to_string());
+                println!("{}: {} = {value}", self.displays.len() - 1, expr.yellow());
+            }
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+
+    pub fn handle_undisplay(&mut self, arg: &str) {
+        if arg.trim().is_empty() {
+            self.displays.clear();
+            println!("Displays cleared.");
+        } else if let Ok(n) = parse_number::<usize>(arg.trim()) {
+            if n < self.displays.len() {
+                let removed = self.displays.remove(n);
+                println!("Display '{removed}' removed.");
+            } else {
+                eprintln!("No display {n}.");
+            }
+        } else {
+            eprintln!("'undisplay' requires a numeric argument.");
+        }
+    }
+
+    pub fn handle_until(&mut self, arg: &str) {
+        if self.dbgcpu.memory.iter().all(|&x| x == 0) {
+            eprintln!("{}", "CPU memory is empty. Load the program first.".red());
+            return;
+        }
+        match self.resolve_address(arg.trim()) {
+            Some(target) => {
+                self.run_until(Some(target));
+                self.print_displays();
+            }
+            None => eprintln!("'until' requires a numeric address or a label."),
+        }
+    }
+
+    // animate [delay in milliseconds]
+    pub fn handle_animate(&mut self, arg: &str) {
+        if self.dbgcpu.memory.iter().all(|&x| x == 0) {
+            eprintln!("{}", "CPU memory is empty. Load the program first.".red());
+            return;
+        }
+        let delay = if arg.trim().is_empty() {
+            ANIMATE_DEFAULT_DELAY
+        } else if let Ok(n) = parse_number::<u64>(arg.trim()) {
+            n
+        } else {
+            eprintln!("'animate' requires a numeric delay in milliseconds.");
+            return;
+        };
+
+        self.dbgcpu.running = true;
+        self.interrupted.store(false, Ordering::SeqCst);
+        self.executing.store(true, Ordering::SeqCst);
+        let mut first = true;
+        while self.dbgcpu.running && !self.interrupted.load(Ordering::SeqCst) {
+            if !first && self.breakpoints.contains(&self.dbgcpu.pc) {
+                break;
+            }
+            first = false;
+            let crashed = !self.step();
+            self.cls();
+            self.print_location();
+            self.print_displays();
+            if crashed {
+                break;
+            }
+            thread::sleep(Duration::from_millis(delay));
+        }
+        self.executing.store(false, Ordering::SeqCst);
+        if self.interrupted.swap(false, Ordering::SeqCst) {
+            println!(
+                "{} at program counter {}.",
+                "Interrupted".yellow(),
+                self.dbgcpu.pc
+            );
+        } else if self.breakpoints.contains(&self.dbgcpu.pc) {
+            println!("Breakpoint {} reached.", self.dbgcpu.pc);
+        }
+    }
+
+    pub fn print_location(&self) {
+        let pc = self.dbgcpu.pc;
+        let label = self
+            .symbols
+            .symbolize(pc)
+            .map(|name| format!(" <{name}>"))
+            .unwrap_or_default();
+        println!(
+            "{}{}: {}",
+            format!("x{pc:04X}").magenta(),
+            label.blue(),
+            CPU::decode_word(self.dbgcpu.memory[pc as usize])
+                .to_string()
+                .green()
+        );
+    }
+
+    pub fn print_displays(&self) {
+        for (index, expr) in self.displays.iter().enumerate() {
+            match self.evaluate_display(expr) {
+                Ok(value) => println!("{index}: {} = {value}", expr.yellow()),
+                Err(e) => println!("{index}: {} = {}", expr.yellow(), e.red()),
+            }
+        }
+    }
+
+    pub fn evaluate_display(&self, expr: &str) -> Result<String, String> {
+        let cpu = &self.dbgcpu;
+        let (head, rest) = Self::parse_command(expr);
+        match head.to_lowercase().as_str() {
+            "r0" | "r1" | "r2" | "r3" | "r4" | "r5" => {
+                let n = head[1..].parse::<usize>().unwrap();
+                Ok(cpu.int_reg[n].to_string())
+            }
+            "r6" => Ok(cpu.float_reg[0].to_string()),
+            "r7" => Ok(cpu.float_reg[1].to_string()),
+            "pc" => Ok(format!("x{:04X}", cpu.pc)),
+            "sp" => Ok(format!("x{:04X}", cpu.sp)),
+            "bp" => Ok(format!("x{:04X}", cpu.bp)),
+            "ir" => Ok(format!("{:016b}", cpu.ir)),
+            "str" => {
+                let addr = self
+                    .resolve_address(rest.trim())
+                    .ok_or_else(|| format!("cannot resolve '{}'", rest.trim()))?;
+                // .asciiz writes one character per word
+                let text: String = cpu.memory[addr as usize..]
+                    .iter()
+                    .take_while(|&&w| (1..=127).contains(&w))
+                    .take(DISPLAY_STRING_MAX)
+                    .map(|&w| w as u8 as char)
+                    .collect();
+                Ok(format!("{text:?}"))
+            }
+            _ => {
+                // [addr] or [label] reads a word; a bare label reads the word it marks
+                let addr = match expr.strip_prefix('[').and_then(|e| e.strip_suffix(']')) {
+                    Some(inner) => self.resolve_address(inner.trim()),
+                    None => self.symbols.lookup(expr),
+                }
+                .ok_or_else(|| format!("cannot evaluate '{expr}'"))?;
+                let word = cpu.memory[addr as usize];
+                Ok(format!("{word} ({word:04X})"))
+            }
+        }
+    }
+
+    pub fn resolve_address(&self, text: &str) -> Option<u16> {
+        parse_number::<u16>(text)
+            .ok()
+            .or_else(|| self.symbols.lookup(text))
+    }
+}
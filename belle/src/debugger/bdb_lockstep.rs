@@ -0,0 +1,255 @@
+use crate::bdb_utils::{parse_number, step_cpu};
+use crate::*;
+use colored::*;
+use std::sync::atomic::Ordering;
+
+const COLUMN_WIDTH: usize = 28;
+const LOCKSTEP_MAX_MEMORY_SHOWN: usize = 16;
+
+/// A second ROM stepped alongside the one being debugged
+pub struct Lockstep {
+    pub exe: String,
+    pub cpu: CPU,
+}
+
+impl BDB {
+    // lockstep <rom>, lockstep [N], lockstep show, or lockstep off
+    pub fn handle_lockstep(&mut self, arg: &str) {
+        let arg = arg.trim();
+        match arg {
+            "off" => {
+                self.lockstep = None;
+                println!("Lockstep disabled.");
+            }
+            "show" => match &self.lockstep {
+                Some(lockstep) => self.print_side_by_side(lockstep, &[]),
+                None => eprintln!("No second ROM loaded. Use 'lockstep <rom>' first."),
+            },
+            "" => {
+                self.step_lockstep(None);
+            }
+            _ => {
+                if let Ok(n) = parse_number::<usize>(arg) {
+                    self.step_lockstep(Some(n));
+                } else {
+                    self.load_lockstep(arg);
+                }
+            }
+        }
+    }
+
+    fn load_lockstep(&mut self, path: &str) {
+        let load = |exe: &str| -> Result<CPU, String> {
+            let mut cpu = BDB::new_cpu();
+            let rom = create_rom(exe).map_err(|e| format!("Failed to read '{exe}': {e}"))?;
+            cpu.load_rom(&rom).map_err(|e| render_emu_error(&e))?;
+            Ok(cpu)
+        };
+        let (primary, other) = match (load(&self.exe), load(path)) {
+            (Ok(primary), Ok(other)) => (primary, other),
+            (Err(e), _) | (_, Err(e)) => {
+                eprintln!("{e}");
+                return;
+            }
+        };
+        self.lockstep = Some(Lockstep {
+            exe: path.to_string(),
+            cpu: other,
+        });
+        self.dbgcpu = primary;
+        println!(
+            "Loaded '{}' and '{path}' for lockstep. Both programs were reset.",
+            self.exe
+        );
+    }
+
+    // steps both CPUs until they diverge, one halts or crashes, a breakpoint, Ctrl-C or N steps,
+    // returning what diverged
+    pub fn step_lockstep(&mut self, limit: Option<usize>) -> Vec<String> {
+        let Some(mut lockstep) = self.lockstep.take() else {
+            eprintln!("No second ROM loaded. Use 'lockstep <rom>' first.");
+            return Vec::new();
+        };
+        self.dbgcpu.running = true;
+        lockstep.cpu.running = true;
+        self.dbgcpu.writes = Some(Vec::new());
+        lockstep.cpu.writes = Some(Vec::new());
+        self.interrupted.store(false, Ordering::SeqCst);
+        self.executing.store(true, Ordering::SeqCst);
+
+        let mut steps = 0;
+        let mut divergence = Vec::new();
+        let mut last = (self.dbgcpu.pc, lockstep.cpu.pc);
+        while self.dbgcpu.running && lockstep.cpu.running {
+            if limit.is_some_and(|n| steps >= n)
+                || self.interrupted.load(Ordering::SeqCst)
+                || (steps > 0 && self.breakpoints.contains(&self.dbgcpu.pc))
+            {
+                break;
+            }
+            last = (self.dbgcpu.pc, lockstep.cpu.pc);
+            let primary_ok = step_cpu(&mut self.dbgcpu);
+            let other_ok = step_cpu(&mut lockstep.cpu);
+            steps += 1;
+            divergence = diverging_fields(&mut self.dbgcpu, &mut lockstep.cpu);
+            if !primary_ok || !other_ok || !divergence.is_empty() {
+                break;
+            }
+        }
+        self.executing.store(false, Ordering::SeqCst);
+        self.dbgcpu.writes = None;
+        lockstep.cpu.writes = None;
+
+        let instruction = |cpu: &CPU, pc: u16| {
+            format!("x{pc:04X}: {}", CPU::decode_word(cpu.memory[pc as usize]))
+        };
+        println!(
+            "{steps} instruction{} stepped.",
+            if steps == 1 { "" } else { "s" }
+        );
+        if self.interrupted.swap(false, Ordering::SeqCst) {
+            println!("{}.", "Interrupted".yellow());
+        } else if !divergence.is_empty() {
+            println!(
+                "{} after {} | {}",
+                "Diverged".red().bold(),
+                instruction(&self.dbgcpu, last.0).green(),
+                instruction(&lockstep.cpu, last.1).green()
+            );
+        } else if self.dbgcpu.err || lockstep.cpu.err {
+            println!("{}.", "Crashed".red());
+        } else if !self.dbgcpu.running || !lockstep.cpu.running {
+            println!("Halted without diverging.");
+        } else if self.breakpoints.contains(&self.dbgcpu.pc) {
+            println!("Breakpoint {} reached.", self.dbgcpu.pc);
+        }
+        self.print_side_by_side(&lockstep, &divergence);
+        self.lockstep = Some(lockstep);
+        divergence
+    }
+
+    fn print_side_by_side(&self, lockstep: &Lockstep, divergence: &[String]) {
+        let (left, right) = (&self.dbgcpu, &lockstep.cpu);
+        println!(
+            "{:>6}  {:<width$} {:<width$}",
+            "",
+            fit(&self.exe).bright_white(),
+            fit(&lockstep.exe).bright_white(),
+            width = COLUMN_WIDTH
+        );
+        let row = |name: &str, l: String, r: String| {
+            let line = format!("{name:>6}  {:<width$} {r}", l, width = COLUMN_WIDTH);
+            if divergence.iter().any(|d| d == name) {
+                println!("{}", line.red());
+            } else {
+                println!("{line}");
+            }
+        };
+        let next = |cpu: &CPU| {
+            format!(
+                "x{:04X} {}",
+                cpu.pc,
+                CPU::decode_word(cpu.memory[cpu.pc as usize])
+            )
+        };
+        row("pc", next(left), next(right));
+        for n in 0..6 {
+            row(
+                &format!("r{n}"),
+                left.int_reg[n].to_string(),
+                right.int_reg[n].to_string(),
+            );
+        }
+        for n in 0..2 {
+            row(
+                &format!("r{}", n + 6),
+                left.float_reg[n].to_string(),
+                right.float_reg[n].to_string(),
+            );
+        }
+        row(
+            "sp",
+            format!("x{:04X}", left.sp),
+            format!("x{:04X}", right.sp),
+        );
+        row(
+            "bp",
+            format!("x{:04X}", left.bp),
+            format!("x{:04X}", right.bp),
+        );
+        row("zf", left.zflag.to_string(), right.zflag.to_string());
+        row("of", left.oflag.to_string(), right.oflag.to_string());
+        row("rf", left.rflag.to_string(), right.rflag.to_string());
+        row("sf", left.sflag.to_string(), right.sflag.to_string());
+
+        let addresses: Vec<&String> = divergence.iter().filter(|d| d.starts_with('x')).collect();
+        for addr in addresses.iter().take(LOCKSTEP_MAX_MEMORY_SHOWN) {
+            let index = usize::from_str_radix(&addr[1..], 16).unwrap_or_default();
+            row(
+                addr,
+                format!("{:04X}", left.memory[index]),
+                format!("{:04X}", right.memory[index]),
+            );
+        }
+        if addresses.len() > LOCKSTEP_MAX_MEMORY_SHOWN {
+            println!(
+                "... and {} more diverging words.",
+                addresses.len() - LOCKSTEP_MAX_MEMORY_SHOWN
+            );
+        }
+    }
+}
+
+// names of the registers, flags and memory addresses that differ between the two CPUs.
+// the program counter is left out since the two programs may be laid out differently,
+// and memory is only compared where either side stored to it since the last step,
+// which empties both write logs
+fn diverging_fields(primary: &mut CPU, other: &mut CPU) -> Vec<String> {
+    let mut fields = Vec::new();
+    for n in 0..6 {
+        if primary.int_reg[n] != other.int_reg[n] {
+            fields.push(format!("r{n}"));
+        }
+    }
+    for n in 0..2 {
+        if primary.float_reg[n].to_bits() != other.float_reg[n].to_bits() {
+            fields.push(format!("r{}", n + 6));
+        }
+    }
+    let flags = [
+        ("sp", primary.sp == other.sp),
+        ("bp", primary.bp == other.bp),
+        ("zf", primary.zflag == other.zflag),
+        ("of", primary.oflag == other.oflag),
+        ("rf", primary.rflag == other.rflag),
+        ("sf", primary.sflag == other.sflag),
+    ];
+    fields.extend(
+        flags
+            .iter()
+            .filter(|(_, same)| !same)
+            .map(|(name, _)| name.to_string()),
+    );
+    let mut written: Vec<u16> = primary
+        .writes
+        .iter_mut()
+        .flat_map(|w| w.drain(..))
+        .collect();
+    written.extend(other.writes.iter_mut().flat_map(|w| w.drain(..)));
+    written.sort_unstable();
+    written.dedup();
+    for addr in written {
+        if primary.memory[addr as usize] != other.memory[addr as usize] {
+            fields.push(format!("x{addr:04X}"));
+        }
+    }
+    fields
+}
+
+fn fit(path: &str) -> String {
+    let name = std::path::Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string());
+    name.chars().take(COLUMN_WIDTH).collect()
+}
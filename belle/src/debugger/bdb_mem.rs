@@ -0,0 +1,199 @@
+use crate::bdb_utils::parse_number;
+use crate::*;
+use colored::*;
+use std::ops::RangeInclusive;
+
+const EXAMINE_WORDS_PER_ROW: usize = 4;
+const EXAMINE_DEFAULT_COUNT: usize = 8;
+const DISAS_CONTEXT: usize = 8;
+const FIND_MAX_SHOWN: usize = 32;
+
+impl BDB {
+    // x/N addr
+    pub fn handle_examine(&mut self, cmd: &str, arg: &str) {
+        let count = match cmd.split_once('/') {
+            Some((_, n)) => match parse_number::<usize>(n) {
+                Ok(n) if n > 0 => n,
+                _ => {
+                    eprintln!("'x' requires a positive word count, e.g. 'x/16 0x100'.");
+                    return;
+                }
+            },
+            None => EXAMINE_DEFAULT_COUNT,
+        };
+        let start = if arg.trim().is_empty() {
+            self.dbgcpu.pc as usize
+        } else if let Ok(n) = parse_number::<usize>(arg.trim()) {
+            n
+        } else {
+            eprintln!("'x' requires a numeric address.");
+            return;
+        };
+        if start >= MEMORY_SIZE {
+            eprintln!("Address {start} is outside of memory.");
+            return;
+        }
+        let end = start.saturating_add(count).min(MEMORY_SIZE);
+
+        for row in (start..end).step_by(EXAMINE_WORDS_PER_ROW) {
+            let words = &self.dbgcpu.memory[row..(row + EXAMINE_WORDS_PER_ROW).min(end)];
+            let hex: Vec<String> = words.iter().map(|w| format!("{w:04X}")).collect();
+            let dec: Vec<String> = words.iter().map(|w| format!("{w:>6}")).collect();
+            let ascii: String = words
+                .iter()
+                .map(|&w| match w {
+                    32..=126 => w as u8 as char,
+                    _ => '.',
+                })
+                .collect();
+            println!(
+                "{}: {:<width_hex$} │ {:<width_dec$} │ {}",
+                format!("x{row:04X}").magenta(),
+                hex.join(" ").bright_white(),
+                dec.join(" ").bright_green(),
+                ascii,
+                width_hex = EXAMINE_WORDS_PER_ROW * 5 - 1,
+                width_dec = EXAMINE_WORDS_PER_ROW * 7 - 1,
+            );
+        }
+    }
+
+    // disas, or disas start end
+    pub fn handle_disassemble(&mut self, arg: &str) {
+        let range = match self.disassembly_range(arg) {
+            Ok(range) => range,
+            Err(e) => {
+                eprintln!("{e}");
+                return;
+            }
+        };
+
+        for addr in range {
+            let word = self.dbgcpu.memory[addr];
+            let marker = if addr == self.dbgcpu.pc as usize {
+                "=>".green().bold()
+            } else {
+                "  ".normal()
+            };
+            let breakpoint = if self.breakpoints.contains(&(addr as u16)) {
+                "●".red()
+            } else {
+                " ".normal()
+            };
+            println!(
+                "{breakpoint}{marker} {}: {:04X}  {}",
+                format!("x{addr:04X}").magenta(),
+                word,
+                CPU::decode_word(word).to_string().green()
+            );
+        }
+    }
+
+    // the addresses 'disas' shows, around the program counter by default
+    pub(crate) fn disassembly_range(&self, arg: &str) -> Result<RangeInclusive<usize>, &str> {
+        let args: Vec<&str> = arg.split_whitespace().collect();
+        let (start, end) = match args.as_slice() {
+            [] => {
+                let pc = self.dbgcpu.pc as usize;
+                (pc.saturating_sub(DISAS_CONTEXT), pc + DISAS_CONTEXT)
+            }
+            [start, end] => match (parse_number::<usize>(start), parse_number::<usize>(end)) {
+                (Ok(s), Ok(e)) if s <= e && s < MEMORY_SIZE => (s, e),
+                _ => return Err("'disas' requires a numeric start and end address."),
+            },
+            _ => return Err("'disas' takes zero or two arguments."),
+        };
+        Ok(start..=end.min(MEMORY_SIZE - 1))
+    }
+
+    // find value [start end], or find "string" [start end]
+    pub fn handle_find(&mut self, arg: &str) {
+        let arg = arg.trim();
+        let (needle, rest) = if let Some(quoted) = arg.strip_prefix('"') {
+            match parse_quoted(quoted) {
+                Some((s, rest)) if !s.is_empty() => {
+                    (s.chars().map(|c| c as u16).collect::<Vec<u16>>(), rest)
+                }
+                _ => {
+                    eprintln!("'find' requires a non-empty, closed string.");
+                    return;
+                }
+            }
+        } else {
+            let (value, rest) = arg.split_once(' ').unwrap_or((arg, ""));
+            let parsed = parse_number::<u16>(value)
+                .or_else(|_| parse_number::<i16>(value).map(|v| v as u16));
+            match parsed {
+                Ok(v) => (vec![v], rest),
+                Err(_) => {
+                    eprintln!("'find' requires a numeric value or a string.");
+                    return;
+                }
+            }
+        };
+
+        let range: Vec<&str> = rest.split_whitespace().collect();
+        let (start, end) = match range.as_slice() {
+            [] => (0, MEMORY_SIZE - 1),
+            [start, end] => match (parse_number::<usize>(start), parse_number::<usize>(end)) {
+                (Ok(s), Ok(e)) if s <= e && e < MEMORY_SIZE => (s, e),
+                _ => {
+                    eprintln!("'find' requires a numeric start and end address.");
+                    return;
+                }
+            },
+            _ => {
+                eprintln!("'find' takes a value and an optional start and end address.");
+                return;
+            }
+        };
+
+        let matches = find_words(&self.dbgcpu.memory[..], &needle, start, end);
+        if matches.is_empty() {
+            println!("Not found.");
+            return;
+        }
+        for addr in matches.iter().take(FIND_MAX_SHOWN) {
+            let word = self.dbgcpu.memory[*addr];
+            println!(
+                "Found at {}: {word:04X} ({word})",
+                format!("x{addr:04X}").magenta(),
+            );
+        }
+        if matches.len() > FIND_MAX_SHOWN {
+            println!("... and {} more.", matches.len() - FIND_MAX_SHOWN);
+        }
+    }
+}
+
+// every address in start..=end where the words of `needle` begin
+pub(crate) fn find_words(memory: &[u16], needle: &[u16], start: usize, end: usize) -> Vec<usize> {
+    memory[start..=end]
+        .windows(needle.len())
+        .enumerate()
+        .filter(|(_, window)| *window == needle)
+        .map(|(index, _)| start + index)
+        .collect()
+}
+
+// parses the remainder of a string literal after the opening quote,
+// returning its contents and whatever follows the closing quote
+pub(crate) fn parse_quoted(input: &str) -> Option<(String, &str)> {
+    let mut result = String::new();
+    let mut chars = input.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Some((result, &input[index + 1..])),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => result.push('\n'),
+                Some((_, 'r')) => result.push('\r'),
+                Some((_, 't')) => result.push('\t'),
+                Some((_, '0')) => result.push('\0'),
+                Some((_, v)) => result.push(v),
+                None => return None,
+            },
+            _ => result.push(c),
+        }
+    }
+    None
+}
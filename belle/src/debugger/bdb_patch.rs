@@ -0,0 +1,149 @@
+use crate::bdb_utils::parse_number;
+use crate::*;
+use colored::*;
+use std::{
+    fs::File,
+    io::{self, Write},
+};
+
+/// A change made to memory with the `asm` command
+#[derive(Debug, Clone)]
+pub struct Patch {
+    pub address: u16,
+    pub source: String,
+    pub old: Vec<u16>,
+    pub new: Vec<u16>,
+}
+
+impl BDB {
+    // asm <addr> <instruction>
+    pub fn handle_assemble(&mut self, arg: &str) {
+        let (addr, source) = match arg.trim().split_once(' ') {
+            Some((a, s)) if !s.trim().is_empty() => (a, s.trim()),
+            _ => {
+                eprintln!(
+                    "'asm' requires an address and an instruction, e.g. 'asm 0x100 mov r0, 4'."
+                );
+                return;
+            }
+        };
+        let Ok(address) = parse_number::<u16>(addr) else {
+            eprintln!("'asm' requires a numeric address.");
+            return;
+        };
+
+        let words = match assemble_at(source, address, &self.symbols) {
+            Ok(w) if !w.is_empty() => w,
+            Ok(_) => {
+                eprintln!("'{source}' did not produce any code.");
+                return;
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                return;
+            }
+        };
+        let start = address as usize;
+        if start + words.len() > MEMORY_SIZE {
+            eprintln!("{}", EmuError::MemoryOverflow());
+            return;
+        }
+
+        let old = self.dbgcpu.memory[start..start + words.len()].to_vec();
+        for (offset, (&before, &after)) in old.iter().zip(words.iter()).enumerate() {
+            let addr = start + offset;
+            println!(
+                "{} {}: {before:04X}  {}",
+                "-".red(),
+                format!("x{addr:04X}").magenta(),
+                CPU::decode_word(before).to_string().red()
+            );
+            println!(
+                "{} {}: {after:04X}  {}",
+                "+".green(),
+                format!("x{addr:04X}").magenta(),
+                CPU::decode_word(after).to_string().green()
+            );
+        }
+        self.dbgcpu.memory[start..start + words.len()].copy_from_slice(&words);
+        self.dbgcpu.mark_initialised(start..start + words.len());
+        self.patches.push(Patch {
+            address,
+            source: source.to_string(),
+            old,
+            new: words,
+        });
+    }
+
+    // patches, or patches write <file>
+    pub fn handle_patches(&mut self, arg: &str) {
+        let (sub, path) = Self::parse_command(arg.trim());
+        match sub {
+            "" => {
+                if self.patches.is_empty() {
+                    println!("No patches.");
+                    return;
+                }
+                for (index, patch) in self.patches.iter().enumerate() {
+                    println!(
+                        "{index:>3}: {} ({} word{}) {}",
+                        format!("x{:04X}", patch.address).magenta(),
+                        patch.new.len(),
+                        if patch.new.len() == 1 { "" } else { "s" },
+                        patch.source.green()
+                    );
+                }
+            }
+            "write" if !path.trim().is_empty() => match self.write_patches(path.trim()) {
+                Ok(()) => println!("Wrote {} patches to {}.", self.patches.len(), path.trim()),
+                Err(e) => eprintln!("Failed to write patches: {e}"),
+            },
+            _ => eprintln!("'patches' takes no arguments, or 'write <file>'."),
+        }
+    }
+
+    pub fn write_patches(&self, path: &str) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        writeln!(file, "--- {}", self.exe)?;
+        writeln!(file, "+++ {} (patched)", self.exe)?;
+        for patch in &self.patches {
+            writeln!(
+                file,
+                "@@ x{:04X},{} @@ {}",
+                patch.address,
+                patch.new.len(),
+                patch.source
+            )?;
+            for (offset, word) in patch.old.iter().enumerate() {
+                let addr = patch.address as usize + offset;
+                writeln!(
+                    file,
+                    "-x{addr:04X}: {word:04X}  {}",
+                    CPU::decode_word(*word)
+                )?;
+            }
+            for (offset, word) in patch.new.iter().enumerate() {
+                let addr = patch.address as usize + offset;
+                writeln!(
+                    file,
+                    "+x{addr:04X}: {word:04X}  {}",
+                    CPU::decode_word(*word)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Assembles instruction text that knows no labels.
+/// Diagnostics are printed by basm itself.
+pub fn assemble(source: &str) -> Result<Vec<u16>, String> {
+    assemble_at(source, 0, &SymbolTable::default())
+}
+
+/// Assembles instruction text placed at `address`, which may refer to the
+/// labels of a symbol table
+pub fn assemble_at(source: &str, address: u16, symbols: &SymbolTable) -> Result<Vec<u16>, String> {
+    basm::assemble(source, address, &symbols.labels)
+        .map_err(|_| format!("Failed to assemble '{source}'."))
+}
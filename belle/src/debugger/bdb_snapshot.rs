@@ -0,0 +1,140 @@
+use crate::*;
+use colored::*;
+use std::ops::Range;
+
+impl BDB {
+    // snap, or snap <name>
+    pub fn handle_snapshot(&mut self, arg: &str) {
+        let name = arg.trim();
+        if name.is_empty() {
+            if self.snapshots.is_empty() {
+                println!("No snapshots.");
+            }
+            for (name, cpu) in &self.snapshots {
+                println!(
+                    "{}: taken at {}",
+                    name.yellow(),
+                    format!("x{:04X}", cpu.pc).magenta()
+                );
+            }
+            return;
+        }
+        if name.contains(char::is_whitespace) {
+            eprintln!("Snapshot names cannot contain spaces.");
+            return;
+        }
+        let replaced = self
+            .snapshots
+            .insert(name.to_string(), self.dbgcpu.clone())
+            .is_some();
+        println!(
+            "Snapshot '{name}' {} at {}.",
+            if replaced { "replaced" } else { "taken" },
+            format!("x{:04X}", self.dbgcpu.pc).magenta()
+        );
+    }
+
+    // diff <name>
+    pub fn handle_diff(&mut self, arg: &str) {
+        let name = arg.trim();
+        if name.is_empty() {
+            eprintln!("'diff' requires the name of a snapshot.");
+            return;
+        }
+        let Some(old) = self.snapshots.get(name) else {
+            eprintln!("No snapshot named '{name}'. Type 'snap' to list them.");
+            return;
+        };
+        let new = &self.dbgcpu;
+
+        let registers = register_changes(old, new);
+        let ranges = changed_ranges(&old.memory[..], &new.memory[..]);
+        if registers.is_empty() && ranges.is_empty() {
+            println!("No changes since '{name}'.");
+            return;
+        }
+
+        for (register, before, after) in &registers {
+            println!(
+                "{:>4}: {} -> {}",
+                register.bright_white(),
+                before.red(),
+                after.green()
+            );
+        }
+        if !registers.is_empty() && !ranges.is_empty() {
+            println!();
+        }
+        for range in &ranges {
+            let words = range.len();
+            println!(
+                "{}-{} ({words} word{})",
+                format!("x{:04X}", range.start).magenta(),
+                format!("x{:04X}", range.end - 1).magenta(),
+                if words == 1 { "" } else { "s" }
+            );
+            for addr in range.clone() {
+                let (before, after) = (old.memory[addr], new.memory[addr]);
+                // the stack and data below the start address are not code
+                if addr >= new.starts_at as usize {
+                    println!(
+                        "  {}: {before:04X} -> {after:04X}  {} -> {}",
+                        format!("x{addr:04X}").magenta(),
+                        CPU::decode_word(before).to_string().red(),
+                        CPU::decode_word(after).to_string().green()
+                    );
+                } else {
+                    println!(
+                        "  {}: {before:04X} -> {after:04X}  ({} -> {})",
+                        format!("x{addr:04X}").magenta(),
+                        before as i16,
+                        after as i16
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn register_changes(old: &CPU, new: &CPU) -> Vec<(String, String, String)> {
+    let hex = |addr: u16| format!("x{addr:04X}");
+    let mut changes = Vec::new();
+    let mut compare = |name: String, before: String, after: String| {
+        if before != after {
+            changes.push((name, before, after));
+        }
+    };
+    for (index, (before, after)) in old.int_reg.iter().zip(new.int_reg.iter()).enumerate() {
+        compare(format!("r{index}"), before.to_string(), after.to_string());
+    }
+    for (index, (before, after)) in old.float_reg.iter().zip(new.float_reg.iter()).enumerate() {
+        compare(
+            format!("r{}", index + 6),
+            before.to_string(),
+            after.to_string(),
+        );
+    }
+    compare("pc".into(), hex(old.pc), hex(new.pc));
+    compare("sp".into(), hex(old.sp), hex(new.sp));
+    compare("bp".into(), hex(old.bp), hex(new.bp));
+    compare("zf".into(), old.zflag.to_string(), new.zflag.to_string());
+    compare("of".into(), old.oflag.to_string(), new.oflag.to_string());
+    compare("rf".into(), old.rflag.to_string(), new.rflag.to_string());
+    compare("sf".into(), old.sflag.to_string(), new.sflag.to_string());
+    changes
+}
+
+/// Groups the addresses whose words differ into contiguous ranges
+pub fn changed_ranges(old: &[u16], new: &[u16]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (addr, (before, after)) in old.iter().zip(new.iter()).enumerate() {
+        if before == after {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(range) if range.end == addr => range.end += 1,
+            _ => ranges.push(addr..addr + 1),
+        }
+    }
+    ranges
+}
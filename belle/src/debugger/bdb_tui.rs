@@ -0,0 +1,487 @@
+use crate::bdb_utils::parse_number;
+use crate::*;
+use colored::*;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{self, ClearType},
+};
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    sync::atomic::Ordering,
+    time::Duration,
+};
+
+const REGISTER_PANE_HEIGHT: usize = 9;
+const LEFT_PANE_WIDTH: usize = 46;
+const MIN_WIDTH: u16 = 80;
+const MIN_HEIGHT: u16 = 20;
+
+struct TuiState {
+    input: String,
+    status: String,
+    mem_view: usize,
+    quit: bool,
+    pending: VecDeque<Event>, // keys typed while a program was running
+}
+
+impl BDB {
+    pub fn run_tui(&mut self) -> io::Result<()> {
+        self.install_interrupt_handler();
+        if let Ok(rom) = create_rom(&self.exe) {
+            if let Err(e) = self.dbgcpu.load_rom(&rom) {
+                eprintln!("{}", render_emu_error(&e));
+                return Ok(());
+            }
+        }
+
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+        let mut state = TuiState {
+            input: String::new(),
+            status: String::from("Type 'h' for help. Enter on an empty line steps."),
+            mem_view: self.dbgcpu.starts_at as usize & !0x7,
+            quit: false,
+            pending: VecDeque::new(),
+        };
+        let result = self.tui_loop(&mut state);
+
+        execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        result
+    }
+
+    fn tui_loop(&mut self, state: &mut TuiState) -> io::Result<()> {
+        while !state.quit {
+            self.draw(state)?;
+            let event = match state.pending.pop_front() {
+                Some(event) => event,
+                None => event::read()?,
+            };
+            let Event::Key(key) = event else {
+                continue; // resizes are picked up by the next draw
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let rows = self.memory_rows()?;
+            match key.code {
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    state.quit = true;
+                }
+                KeyCode::Char(c) => state.input.push(c),
+                KeyCode::Backspace => {
+                    state.input.pop();
+                }
+                KeyCode::Esc => state.input.clear(),
+                KeyCode::Up => state.mem_view = state.mem_view.saturating_sub(rows.1),
+                KeyCode::Down => state.mem_view = (state.mem_view + rows.1).min(MEMORY_SIZE - 1),
+                KeyCode::PageUp => state.mem_view = state.mem_view.saturating_sub(rows.0 * rows.1),
+                KeyCode::PageDown => {
+                    state.mem_view = (state.mem_view + rows.0 * rows.1).min(MEMORY_SIZE - 1)
+                }
+                KeyCode::Enter => {
+                    let command = std::mem::take(&mut state.input);
+                    self.tui_command(command.trim(), state);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn tui_command(&mut self, command: &str, state: &mut TuiState) {
+        let (cmd, arg) = Self::parse_command(command);
+        let arg = arg.trim();
+        state.status = match cmd.to_lowercase().as_str() {
+            "" | "e" | "s" => self.tui_step(),
+            "r" | "c" => self.tui_run(&mut state.pending),
+            "q" | "quit" | ":q" => {
+                state.quit = true;
+                String::new()
+            }
+            "l" => match create_rom(&self.exe) {
+                Ok(rom) => match self.dbgcpu.load_rom(&rom) {
+                    Ok(()) => format!("Loaded {}.", self.exe),
+                    Err(e) => render_emu_error(&e),
+                },
+                Err(e) => e.to_string(),
+            },
+            "rs" => {
+                self.dbgcpu = Self::new_cpu();
+                String::from("CPU reset.")
+            }
+            "spc" => match parse_number::<u16>(arg) {
+                Ok(n) => {
+                    self.dbgcpu.pc = n;
+                    format!("Program counter set to {n}.")
+                }
+                Err(_) => String::from("'spc' requires a numeric argument."),
+            },
+            "b" => match u16::from_str_radix(arg, 16) {
+                Ok(n) => {
+                    self.breakpoints.push(n);
+                    format!("Breakpoint {n} added.")
+                }
+                Err(_) => String::from("'b' requires a numeric hex argument."),
+            },
+            "br" => match u16::from_str_radix(arg, 16) {
+                Ok(n) => {
+                    self.breakpoints.retain(|&x| x != n);
+                    format!("Breakpoint {n} removed.")
+                }
+                Err(_) => String::from("'br' requires a numeric hex argument."),
+            },
+            "ba" => {
+                self.breakpoints.clear();
+                String::from("Breakpoints cleared.")
+            }
+            "m" => match parse_number::<usize>(arg) {
+                Ok(n) if n < MEMORY_SIZE => {
+                    state.mem_view = n;
+                    format!("Memory view moved to x{n:04X}.")
+                }
+                _ => String::from("'m' requires a numeric address."),
+            },
+            "h" | "help" => String::from(
+                "e/<enter> step, r run, b/br <hex> breakpoint, ba clear, spc <n>, m <addr>, l load, rs reset, q quit",
+            ),
+            _ => format!("Unknown command: '{command}'"),
+        };
+    }
+
+    fn tui_step(&mut self) -> String {
+        if self.dbgcpu.memory.iter().all(|&x| x == 0) {
+            return String::from("CPU memory is empty. Load the program first.");
+        }
+        let pc = self.dbgcpu.pc;
+        self.dbgcpu.ir = self.dbgcpu.memory[pc as usize] as i16;
+        let parsed_ins = self.dbgcpu.decode_instruction();
+        let message = match self.dbgcpu.execute_instruction(&parsed_ins) {
+            Ok(()) => format!("Executed {parsed_ins} at x{pc:04X}."),
+            Err(e) => {
+                self.dbgcpu.err = true;
+                self.dbgcpu.errmsg = e.kind().to_string();
+                crash_summary(e.kind())
+            }
+        };
+        // interrupts may leave raw mode, which the interface depends on
+        let _ = terminal::enable_raw_mode();
+        message
+    }
+
+    fn tui_run(&mut self, pending: &mut VecDeque<Event>) -> String {
+        if self.dbgcpu.memory.iter().all(|&x| x == 0) {
+            return String::from("CPU memory is empty. Load the program first.");
+        }
+        self.dbgcpu.running = true;
+        self.interrupted.store(false, Ordering::SeqCst);
+        self.executing.store(true, Ordering::SeqCst);
+        let mut cycles: usize = 0;
+        let mut message = None;
+        while !self.breakpoints.contains(&self.dbgcpu.pc) && self.dbgcpu.running {
+            cycles += 1;
+            if cycles.is_multiple_of(4096) && ctrl_c_pressed(pending) {
+                self.interrupted.store(true, Ordering::SeqCst);
+            }
+            if self.interrupted.load(Ordering::SeqCst) {
+                break;
+            }
+            self.dbgcpu.ir = self.dbgcpu.memory[self.dbgcpu.pc as usize] as i16;
+            let parsed_ins = self.dbgcpu.decode_instruction();
+            if let Err(e) = self.dbgcpu.execute_instruction(&parsed_ins) {
+                self.dbgcpu.err = true;
+                self.dbgcpu.errmsg = e.kind().to_string();
+                message = Some(crash_summary(e.kind()));
+                break;
+            }
+        }
+        self.executing.store(false, Ordering::SeqCst);
+        let _ = terminal::enable_raw_mode();
+        if let Some(m) = message {
+            m
+        } else if self.interrupted.swap(false, Ordering::SeqCst) {
+            format!("Interrupted at program counter {}.", self.dbgcpu.pc)
+        } else if self.breakpoints.contains(&self.dbgcpu.pc) {
+            format!("Breakpoint {} reached.", self.dbgcpu.pc)
+        } else {
+            format!("Halted after {cycles} instructions.")
+        }
+    }
+
+    // (rows, words per row) of the memory pane
+    fn memory_rows(&self) -> io::Result<(usize, usize)> {
+        let (width, height) = terminal::size()?;
+        let main_height = (height as usize).saturating_sub(2);
+        let stack_height = (main_height / 3).max(5);
+        let right_width = (width as usize).saturating_sub(LEFT_PANE_WIDTH);
+        // "x0000: " + "FFFF " per word + " |" + one ascii char per word + "|"
+        let words = (right_width.saturating_sub(4 + 7 + 3) / 6).clamp(1, 16);
+        Ok((main_height.saturating_sub(stack_height + 2), words))
+    }
+
+    fn draw(&mut self, state: &TuiState) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        let (width, height) = terminal::size()?;
+        queue!(stdout, terminal::Clear(ClearType::All))?;
+        if width < MIN_WIDTH || height < MIN_HEIGHT {
+            queue!(
+                stdout,
+                cursor::MoveTo(0, 0),
+                Print(format!(
+                    "Terminal too small ({width}x{height}), need {MIN_WIDTH}x{MIN_HEIGHT}."
+                ))
+            )?;
+            return stdout.flush();
+        }
+
+        let main_height = height as usize - 2;
+        let right_width = width as usize - LEFT_PANE_WIDTH;
+        let stack_height = (main_height / 3).max(5);
+
+        let mut left = pane(
+            "Registers",
+            &self.register_lines(),
+            LEFT_PANE_WIDTH,
+            REGISTER_PANE_HEIGHT,
+        );
+        let disassembly_height = main_height - REGISTER_PANE_HEIGHT;
+        left.extend(pane(
+            "Disassembly",
+            &self.disassembly_lines(disassembly_height - 2),
+            LEFT_PANE_WIDTH,
+            disassembly_height,
+        ));
+
+        let mut right = pane(
+            "Stack",
+            &self.stack_lines(stack_height - 2),
+            right_width,
+            stack_height,
+        );
+        let (rows, words) = self.memory_rows()?;
+        right.extend(pane(
+            "Memory",
+            &self.memory_lines(state.mem_view, rows, words),
+            right_width,
+            main_height - stack_height,
+        ));
+
+        for (row, (l, r)) in left.iter().zip(right.iter()).enumerate() {
+            queue!(stdout, cursor::MoveTo(0, row as u16), Print(l), Print(r))?;
+        }
+        queue!(
+            stdout,
+            cursor::MoveTo(0, height - 2),
+            Print(fit(&state.status.yellow().to_string(), width as usize)),
+            cursor::MoveTo(0, height - 1),
+            Print(fit(
+                &format!("{}{}", "(bdb)> ".green(), state.input),
+                width as usize
+            ))
+        )?;
+        stdout.flush()
+    }
+
+    fn register_lines(&self) -> Vec<String> {
+        let cpu = &self.dbgcpu;
+        let flag = |name: &str, set: bool| {
+            if set {
+                name.to_uppercase().green().to_string()
+            } else {
+                name.red().to_string()
+            }
+        };
+        let state = if cpu.err {
+            "CRASHED".bright_red()
+        } else if cpu.running {
+            "RUNNING".green()
+        } else {
+            "HALTED".red()
+        };
+        vec![
+            format!(
+                "r0: {:<8} r1: {:<8} r2: {:<8}",
+                cpu.int_reg[0], cpu.int_reg[1], cpu.int_reg[2]
+            ),
+            format!(
+                "r3: {:<8} r4: {:<8} r5: {:<8}",
+                cpu.int_reg[3], cpu.int_reg[4], cpu.int_reg[5]
+            ),
+            format!("r6: {:<21} r7: {:<8}", cpu.float_reg[0], cpu.float_reg[1]),
+            format!(
+                "pc: {:<8} sp: {:<8} bp: {:<8}",
+                format!("x{:04X}", cpu.pc),
+                format!("x{:04X}", cpu.sp),
+                format!("x{:04X}", cpu.bp)
+            ),
+            format!("ir: {:016b}", cpu.ir),
+            format!(
+                "flags: {} {} {} {}   pushret: {}",
+                flag("z", cpu.zflag),
+                flag("o", cpu.oflag),
+                flag("r", cpu.rflag),
+                flag("s", cpu.sflag),
+                if cpu.pushret {
+                    "set".green()
+                } else {
+                    "unset".red()
+                }
+            ),
+            format!("state: {state}"),
+        ]
+    }
+
+    fn disassembly_lines(&self, rows: usize) -> Vec<String> {
+        let pc = self.dbgcpu.pc as usize;
+        let start = pc
+            .saturating_sub(rows / 3)
+            .min(MEMORY_SIZE.saturating_sub(rows));
+        (start..(start + rows).min(MEMORY_SIZE))
+            .map(|addr| {
+                let word = self.dbgcpu.memory[addr];
+                let marker = if self.breakpoints.contains(&(addr as u16)) {
+                    "●".red().to_string()
+                } else {
+                    " ".to_string()
+                };
+                let line = format!(
+                    "{} x{addr:04X}  {word:04X}  {}",
+                    if addr == pc { ">" } else { " " },
+                    CPU::decode_word(word)
+                );
+                if addr == pc {
+                    format!("{marker}{}", line.green().bold())
+                } else {
+                    format!("{marker}{line}")
+                }
+            })
+            .collect()
+    }
+
+    fn stack_lines(&self, rows: usize) -> Vec<String> {
+        let cpu = &self.dbgcpu;
+        if cpu.sp == cpu.bp {
+            return vec![String::from("(empty)")];
+        }
+        // a downward stack occupies [sp, bp), an upward one (bp, sp]
+        let slots: Vec<usize> = if cpu.sp < cpu.bp {
+            (cpu.sp as usize..cpu.bp as usize).collect()
+        } else {
+            (cpu.bp as usize + 1..=cpu.sp as usize).rev().collect()
+        };
+        let mut lines: Vec<String> = slots
+            .iter()
+            .take(rows)
+            .map(|&addr| {
+                let value = cpu.memory[addr];
+                let marker = if addr == cpu.sp as usize {
+                    "  <- sp".cyan().to_string()
+                } else {
+                    String::new()
+                };
+                format!("x{addr:04X}: {value:<6} ({value:04X}){marker}")
+            })
+            .collect();
+        if slots.len() > rows && rows > 0 {
+            lines.pop();
+            lines.push(format!("... {} more", slots.len() - rows + 1));
+        }
+        lines
+    }
+
+    fn memory_lines(&self, start: usize, rows: usize, words: usize) -> Vec<String> {
+        let cpu = &self.dbgcpu;
+        (0..rows)
+            .map(|row| start + row * words)
+            .take_while(|&addr| addr < MEMORY_SIZE)
+            .map(|addr| {
+                let end = (addr + words).min(MEMORY_SIZE);
+                let mut hex = String::new();
+                let mut ascii = String::new();
+                for index in addr..end {
+                    let value = cpu.memory[index];
+                    let cell = format!("{value:04X}");
+                    if index == cpu.pc as usize {
+                        hex.push_str(&cell.green().bold().to_string());
+                    } else if index == cpu.sp as usize {
+                        hex.push_str(&cell.cyan().to_string());
+                    } else if value == 0 {
+                        hex.push_str(&cell.dimmed().to_string());
+                    } else {
+                        hex.push_str(&cell);
+                    }
+                    hex.push(' ');
+                    ascii.push(match value {
+                        32..=126 => value as u8 as char,
+                        _ => '.',
+                    });
+                }
+                format!("{}: {hex}|{ascii}|", format!("x{addr:04X}").magenta())
+            })
+            .collect()
+    }
+}
+
+// drains the event queue, keeping everything but Ctrl-C for the interface to replay
+fn ctrl_c_pressed(pending: &mut VecDeque<Event>) -> bool {
+    while let Ok(true) = event::poll(Duration::ZERO) {
+        match event::read() {
+            Ok(Event::Key(KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers,
+                ..
+            })) if modifiers.contains(KeyModifiers::CONTROL) => return true,
+            Ok(event) => pending.push_back(event),
+            Err(_) => break,
+        }
+    }
+    false
+}
+
+pub(crate) fn pane(title: &str, lines: &[String], width: usize, height: usize) -> Vec<String> {
+    let inner = width.saturating_sub(4);
+    let mut rows = Vec::with_capacity(height);
+    rows.push(format!(
+        "╭─ {} {}╮",
+        title.bold().blue(),
+        "─".repeat(width.saturating_sub(title.len() + 5))
+    ));
+    for index in 0..height.saturating_sub(2) {
+        let line = lines.get(index).map(String::as_str).unwrap_or("");
+        rows.push(format!("│ {} │", fit(line, inner)));
+    }
+    rows.push(format!("╰{}╯", "─".repeat(width.saturating_sub(2))));
+    rows
+}
+
+// pads or truncates to exactly `width` visible characters
+pub(crate) fn fit(text: &str, width: usize) -> String {
+    let plain = strip_ansi(text);
+    let visible = plain.chars().count();
+    if visible <= width {
+        format!("{text}{}", " ".repeat(width - visible))
+    } else {
+        plain.chars().take(width).collect()
+    }
+}
+
+pub(crate) fn strip_ansi(text: &str) -> String {
+    let mut plain = String::new();
+    let mut inside_ansi = false;
+    for c in text.chars() {
+        if c == '\x1B' {
+            inside_ansi = true;
+        } else if inside_ansi {
+            inside_ansi = !(c == 'm' || c == 'K');
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
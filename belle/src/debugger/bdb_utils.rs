@@ -1,6 +1,9 @@
 use crate::*;
 use colored::*;
-use std::io::{self, Write};
+use std::{
+    io::{self, Write},
+    sync::atomic::Ordering,
+};
 impl BDB {
     pub fn handle_set_breakpoint(&mut self, arg: &str) {
         if let Ok(n) = u16::from_str_radix(arg.trim(), 16) {
@@ -23,9 +26,24 @@ impl BDB {
                 ("spc", "Set program counter to a given value"),
                 ("p", "Print value in memory"),
                 ("pk", "Set a new value for a location in memory"),
+                ("set", "Set a register, pointer or flag"),
+                ("asm", "Assemble an instruction into memory"),
+                ("patches", "List or write out patches made with 'asm'"),
                 ("a", "Print all memory"),
+                ("x/N", "Dump N words of memory in hex, decimal and ASCII"),
+                ("disas", "Disassemble around the PC or a range of memory"),
+                ("find", "Search memory for a value or string"),
                 ("wb", "Print CPU's starting memory address"),
-                ("e", "Execute instruction"),
+                ("e", "Execute one or N instructions"),
+                ("until", "Run until an address or label is reached"),
+                ("animate", "Single-step with a delay, refreshing displays"),
+                ("display", "Show an expression after every stop"),
+                ("undisplay", "Remove one or all displays"),
+                ("snap", "Take a named snapshot of memory and registers"),
+                ("diff", "Compare a snapshot with the current state"),
+                ("lockstep", "Step a second ROM alongside this one"),
+                ("history", "Show the last instructions that were executed"),
+                ("bt", "Show the return addresses on the stack"),
                 ("w", "View the state of the CPU"),
                 ("b", "Set a breakpoint"),
                 ("br", "Remove a breakpoint"),
@@ -45,15 +63,30 @@ impl BDB {
                 "q" => println!("'quit' takes no arguments. Exits the debugger."),
                 "h" => println!("'help' takes zero or one argument. Prints command information."),
                 "l" => println!("'load' takes no arguments. Loads the CPU's memory with the program."),
-                "r" => println!("'run' takes no arguments. Executes the CPU with the loaded data."),
+                "r" => println!("'run' takes no arguments. Executes the CPU with the loaded data.\nPress Ctrl-C while running to pause at the current program counter."),
                 "spc" => println!("'set program counter' takes one argument to set the CPU's program counter."),
                 "p" | "pmem" => println!("'print memory' takes one argument. Prints the value at the specified memory address."),
-                "e" => println!("'execute' takes no arguments. Executes the instruction at the current program counter."),
+                "e" => println!("'execute' takes zero or one argument. Executes N instructions (default 1) from the current program counter,\nstopping early at a breakpoint or halt. Prints the CPU state, or the displays if there are any."),
+                "until" | "u" => println!("'until' takes one argument. Runs until the program counter reaches an address or label."),
+                "animate" => println!("'animate' takes zero or one argument. Single-steps with a delay in milliseconds (default 250),\nrefreshing the displays, until a breakpoint, halt or Ctrl-C."),
+                "display" => println!("'display' takes zero or one argument. Adds an expression that is printed after every stop:\na register (r0-r7, pc, sp, bp, ir), a memory word ([addr] or a label), or a string ('str <addr|label>').\nWithout an argument, prints every display."),
+                "undisplay" => println!("'undisplay' takes zero or one argument. Removes the display with the given number, or all displays."),
+                "snap" | "snapshot" => println!("'snap' takes zero or one argument. Saves memory and registers under a name,\nreplacing any snapshot with the same name. Without an argument, lists the snapshots."),
+                "diff" => println!("'diff' takes one argument. Lists every register and memory word that changed since the named snapshot,\ngrouped into contiguous ranges, with the old and new instruction for words in the program."),
+                "lockstep" => println!("'lockstep' takes one argument. 'lockstep <rom>' loads a second ROM and resets both programs.\n'lockstep' or 'lockstep N' then steps both programs together until registers, flags or written memory\ndiverge, either program halts or crashes, a breakpoint is reached, or N instructions have run.\n'lockstep show' prints both states side by side, and 'lockstep off' unloads the second ROM."),
+                "history" => println!("'history' takes no arguments. Prints the last 32 instructions that were executed,\nincluding those recorded in a crash dump opened with '--core'."),
+                "bt" => println!("'backtrace' takes no arguments. Lists the return addresses on the stack, innermost first,\nwith their labels and source lines when the ROM has a symbol file."),
                 "cls" => println!("'clear' takes no arguments. Resets the cursor to the top left of the terminal."),
                 "wb" => println!("'where begins' takes no arguments. Prints the starting memory address of the CPU."),
                 "a" => println!("'all instructions' takes no arguments. Prints all memory as instructions."),
                 "w" => println!("'w' takes no arguments. Prints the current state of the CPU."),
+                "x" | "x/n" => println!("'x/N' takes one optional argument. Dumps N words (default 8) starting at an address (default pc)\nin hex, decimal and ASCII columns, e.g. 'x/16 0x100'."),
+                "disas" => println!("'disas' takes zero or two arguments. Disassembles the words around the program counter,\nor every word from a start to an end address, e.g. 'disas 0x100 0x120'."),
+                "find" => println!("'find' takes a value or a quoted string and an optional start and end address.\nPrints every address where the value or .asciiz string occurs, e.g. 'find \"hello\" 0 0x400'."),
                 "pk" => println!("'pk' takes one argument. Sets a new value for a memory location."),
+                "asm" => println!("'asm' takes an address and an instruction. Assembles the instruction with basm, writes it\ninto memory and shows what it replaced, e.g. 'asm 0x104 add r0, 2'."),
+                "patches" => println!("'patches' takes no arguments, or 'write <file>'. Lists patches made with 'asm',\nor writes them to a file as a diff."),
+                "set" => println!("'set' takes two arguments. Sets r0-r5, r6/r7, pc, sp, bp, a flag (z, o, r, s), pushret\nor a memory word, e.g. 'set r0 0x10', 'set r6 1.5', 'set z 1', 'set pushret off', 'set [0x200] 7'."),
                 "im" => println!("'info memory' takes one argument. Prints the value in memory after the CPU has run."),
                 "rs" => println!("'reset' takes no arguments. Resets all parts of the emulator."),
                 "b" => println!("'breakpoint' takes one argument. Sets a breakpoint at a specified memory address."),
@@ -70,20 +103,81 @@ impl BDB {
             eprintln!("{}", "CPU memory is empty. Load the program first.".red());
             return;
         }
+        self.run_until(None);
+        self.print_displays();
+    }
 
+    // runs until a breakpoint, the target address, a halt, a crash or Ctrl-C
+    pub fn run_until(&mut self, target: Option<u16>) {
         self.dbgcpu.running = true;
-        while !self.breakpoints.contains(&self.dbgcpu.pc) && self.dbgcpu.running {
-            self.dbgcpu.ir = self.dbgcpu.memory[self.dbgcpu.pc as usize] as i16;
-            let parsed_ins = self.dbgcpu.decode_instruction();
-            if let Err(e) = self.dbgcpu.execute_instruction(&parsed_ins) {
-                eprintln!("{e}");
+        self.interrupted.store(false, Ordering::SeqCst);
+        self.executing.store(true, Ordering::SeqCst);
+        let mut first = true;
+        while self.dbgcpu.running {
+            let at_target = target == Some(self.dbgcpu.pc) && !first;
+            if at_target || (self.breakpoints.contains(&self.dbgcpu.pc) && !first) {
+                break;
+            }
+            first = false;
+            if self.interrupted.load(Ordering::SeqCst) {
+                break;
+            }
+            if !self.step() {
+                break;
             }
         }
-        if self.breakpoints.contains(&self.dbgcpu.pc) {
+        self.executing.store(false, Ordering::SeqCst);
+        if self.interrupted.swap(false, Ordering::SeqCst) {
+            println!(
+                "\n{} at program counter {}.",
+                "Interrupted".yellow(),
+                self.dbgcpu.pc
+            );
+        } else if target == Some(self.dbgcpu.pc) {
+            println!("Reached {}.", self.dbgcpu.pc);
+        } else if self.breakpoints.contains(&self.dbgcpu.pc) {
             println!("Breakpoint {} reached.", self.dbgcpu.pc);
         }
     }
 
+    // executes the instruction at the program counter, returning false if it crashed
+    pub fn step(&mut self) -> bool {
+        step_cpu(&mut self.dbgcpu)
+    }
+
+    pub fn handle_history(&self) {
+        if self.dbgcpu.history.is_empty() {
+            println!("No instructions have been executed.");
+            return;
+        }
+        let last = self.dbgcpu.history.len() - 1;
+        for (index, &(addr, word)) in self.dbgcpu.history.iter().enumerate() {
+            let marker = if index == last { "=>" } else { "  " };
+            println!(
+                "{marker} {}: {word:04X}  {}",
+                format!("x{addr:04X}").magenta(),
+                CPU::decode_word(word).to_string().green()
+            );
+        }
+    }
+
+    pub fn handle_backtrace(&self) {
+        let frames = backtrace(&self.dbgcpu);
+        if frames.is_empty() {
+            println!("No return addresses on the stack.");
+            return;
+        }
+        for (depth, addr) in frames.into_iter().enumerate() {
+            println!(
+                "#{depth} {} {}",
+                self.symbols.describe(addr).magenta(),
+                CPU::decode_word(self.dbgcpu.memory[addr as usize])
+                    .to_string()
+                    .green()
+            );
+        }
+    }
+
     pub fn handle_set_pc(&mut self, arg: &str) {
         if self.dbgcpu.memory.iter().all(|&x| x == 0) {
             eprintln!("{}", "CPU memory is empty. Load the program first.".red());
@@ -121,19 +215,38 @@ impl BDB {
         }
     }
 
-    pub fn handle_execute(&mut self) {
-        self.dbgcpu.ir = self.dbgcpu.memory[self.dbgcpu.pc as usize] as i16;
+    pub fn handle_execute(&mut self, arg: &str) {
+        let count = if arg.trim().is_empty() {
+            1
+        } else if let Ok(n) = parse_number::<usize>(arg.trim()) {
+            n
+        } else {
+            eprintln!("'e' requires a numeric argument.");
+            return;
+        };
 
-        let parsed_ins = self.dbgcpu.decode_instruction();
-        if let Err(e) = self.dbgcpu.execute_instruction(&parsed_ins) {
-            self.dbgcpu.err = true;
-            self.dbgcpu.errmsg = e.only_err();
-            eprintln!("{e}");
+        for executed in 0..count {
+            if executed > 0 && self.breakpoints.contains(&self.dbgcpu.pc) {
+                println!("Breakpoint {} reached.", self.dbgcpu.pc);
+                break;
+            }
+            let halts = matches!(
+                CPU::decode_word(self.dbgcpu.memory[self.dbgcpu.pc as usize]),
+                Instruction::HLT
+            );
+            if !self.step() || halts {
+                break;
+            }
         }
 
-        self.dbgcpu.pmem = false;
-        println!("{}", self.dbgcpu);
-        self.dbgcpu.pmem = true;
+        if self.displays.is_empty() {
+            self.dbgcpu.pmem = false;
+            println!("{}", self.dbgcpu);
+            self.dbgcpu.pmem = true;
+        } else {
+            self.print_location();
+            self.print_displays();
+        }
     }
 
     pub fn handle_print_all_memory(&mut self) {
@@ -178,13 +291,95 @@ impl BDB {
                 return;
             }
             match parse_number::<u16>(&buffer) {
-                Ok(v) => self.dbgcpu.memory[n] = v,
+                Ok(v) => {
+                    self.dbgcpu.memory[n] = v;
+                    self.dbgcpu.mark_initialised(n..n + 1);
+                }
                 Err(e) => eprintln!("{e}"),
             }
         } else {
             eprintln!("'pk' requires a numeric argument.");
         }
     }
+    pub fn handle_set(&mut self, arg: &str) {
+        let (target, value) = match arg.trim().split_once(' ') {
+            Some((t, v)) => (t.trim().to_lowercase(), v.trim()),
+            None => {
+                eprintln!("'set' requires a target and a value, e.g. 'set r0 0x10'.");
+                return;
+            }
+        };
+        let int_value =
+            || parse_number::<u16>(value).or_else(|_| parse_number::<i16>(value).map(|v| v as u16));
+        let flag_value = || match value.to_lowercase().as_str() {
+            "1" | "true" | "set" | "on" => Some(true),
+            "0" | "false" | "unset" | "off" => Some(false),
+            _ => None,
+        };
+
+        match target.as_str() {
+            "r0" | "r1" | "r2" | "r3" | "r4" | "r5" => match int_value() {
+                Ok(v) => {
+                    let n = target[1..].parse::<usize>().unwrap();
+                    self.dbgcpu.int_reg[n] = v;
+                    println!("{target} set to {v}.");
+                }
+                Err(_) => eprintln!("'set {target}' requires a numeric argument."),
+            },
+            "r6" | "r7" => {
+                let parsed = value
+                    .parse::<f32>()
+                    .ok()
+                    .or_else(|| int_value().ok().map(|v| v as i16 as f32));
+                match parsed {
+                    Some(v) => {
+                        let n = if target == "r6" { 0 } else { 1 };
+                        self.dbgcpu.float_reg[n] = v;
+                        println!("{target} set to {v}.");
+                    }
+                    None => eprintln!("'set {target}' requires a numeric argument."),
+                }
+            }
+            "pc" | "sp" | "bp" => match int_value() {
+                Ok(v) => {
+                    match target.as_str() {
+                        "pc" => self.dbgcpu.pc = v,
+                        "sp" => self.dbgcpu.sp = v,
+                        _ => self.dbgcpu.bp = v,
+                    }
+                    println!("{target} set to {v}.");
+                }
+                Err(_) => eprintln!("'set {target}' requires a numeric argument."),
+            },
+            "z" | "zf" | "o" | "of" | "r" | "rf" | "s" | "sf" | "pushret" => match flag_value() {
+                Some(v) => {
+                    match target.as_str() {
+                        "z" | "zf" => self.dbgcpu.zflag = v,
+                        "o" | "of" => self.dbgcpu.oflag = v,
+                        "r" | "rf" => self.dbgcpu.rflag = v,
+                        "s" | "sf" => self.dbgcpu.sflag = v,
+                        _ => self.dbgcpu.pushret = v,
+                    }
+                    println!("{target} {}.", if v { "set" } else { "unset" });
+                }
+                None => eprintln!("'set {target}' requires 1/0, true/false, set/unset or on/off."),
+            },
+            _ if target.starts_with('[') && target.ends_with(']') => {
+                let address = parse_number::<usize>(&target[1..target.len() - 1]);
+                match (address, int_value()) {
+                    (Ok(n), Ok(v)) if n < MEMORY_SIZE => {
+                        self.dbgcpu.memory[n] = v;
+                        self.dbgcpu.mark_initialised(n..n + 1);
+                        println!("{target} set to {v}.");
+                    }
+                    (Ok(_), Err(_)) => eprintln!("'set {target}' requires a numeric argument."),
+                    _ => eprintln!("'set [address]' requires an address inside memory."),
+                }
+            }
+            _ => eprintln!("Unknown target '{target}'. Type 'h set' for a list of targets."),
+        }
+    }
+
     pub fn handle_remove_breakpoint(&mut self, arg: &str) {
         if let Ok(n) = u16::from_str_radix(arg.trim(), 16) {
             self.breakpoints.retain(|&x| x != n);
@@ -197,7 +392,7 @@ impl BDB {
 use std::num::ParseIntError;
 use std::str::FromStr;
 
-trait FromStrRadix: FromStr<Err = ParseIntError> {
+pub(crate) trait FromStrRadix: FromStr<Err = ParseIntError> {
     fn from_str_radix(src: &str, radix: u32) -> Result<Self, ParseIntError>
     where
         Self: Sized;
@@ -215,7 +410,7 @@ macro_rules! impl_from_str_radix {
 
 impl_from_str_radix!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
 
-fn parse_number<T: FromStrRadix>(input: &str) -> Result<T, ParseIntError> {
+pub(crate) fn parse_number<T: FromStrRadix>(input: &str) -> Result<T, ParseIntError> {
     if let Some(v) = input.strip_prefix("0x") {
         T::from_str_radix(v, 16)
     } else if let Some(v) = input.strip_prefix("0b") {
@@ -224,3 +419,17 @@ fn parse_number<T: FromStrRadix>(input: &str) -> Result<T, ParseIntError> {
         input.parse::<T>()
     }
 }
+
+// executes the instruction at a CPU's program counter, returning false if it crashed
+pub(crate) fn step_cpu(cpu: &mut CPU) -> bool {
+    cpu.record_history();
+    cpu.ir = cpu.memory[cpu.pc as usize] as i16;
+    let parsed_ins = cpu.decode_instruction();
+    if let Err(e) = cpu.execute_instruction(&parsed_ins) {
+        cpu.err = true;
+        cpu.errmsg = e.kind().to_string();
+        eprintln!("{}", render_crash(&e));
+        return false;
+    }
+    true
+}
@@ -1,3 +1,357 @@
 pub mod bdb_core;
 pub use bdb_core::*;
+pub mod bdb_display;
+pub mod bdb_lockstep;
+pub use bdb_lockstep::*;
+pub mod bdb_mem;
+pub mod bdb_patch;
+pub use bdb_patch::*;
+pub mod bdb_snapshot;
+pub use bdb_snapshot::*;
+pub mod bdb_tui;
 pub mod bdb_utils;
+
+#[test]
+fn assemble_patch() {
+    assert_eq!(assemble("add r0, 2"), Ok(vec![0x1102]));
+    assert_eq!(assemble(".asciiz \"hi\""), Ok(vec![0x68, 0x69]));
+    assert!(assemble("bogus r0").is_err());
+}
+
+#[test]
+fn snapshot_ranges() {
+    let old = [0, 1, 2, 3, 4, 5, 6];
+    let new = [0, 9, 9, 3, 4, 9, 6];
+    assert_eq!(changed_ranges(&old, &new), vec![1..3, 5..6]);
+    assert!(changed_ranges(&old, &old).is_empty());
+}
+
+#[test]
+fn parse_symbol_file() {
+    let table = crate::SymbolTable::parse(
+        "; basm symbol file\nlabel start 0x0100\nline 0x0100 3 main.asm\nline 0x0101 7 my lib.asm\n",
+    );
+    assert_eq!(table.lookup("start"), Some(0x100));
+    assert_eq!(table.name_at(0x100), Some("start"));
+    assert_eq!(
+        table.lines.get(&0x101),
+        Some(&crate::SourceLine {
+            file: "my lib.asm".to_string(),
+            line: 7
+        })
+    );
+}
+
+#[test]
+fn crash_dump_round_trip() {
+    let mut cpu = crate::CPU::new();
+    cpu.memory[0x100..0x103].copy_from_slice(&[0xE105, 0, 0x4001]);
+    cpu.int_reg[0] = 5;
+    cpu.pc = 0x102;
+    cpu.record_history();
+    let dump = crate::CrashDump::new(&cpu, "test.bin", None);
+    assert_eq!(dump.memory.len(), 2);
+
+    let json = serde_json::to_string(&dump).unwrap();
+    let restored: crate::CrashDump = serde_json::from_str(&json).unwrap();
+    let restored = restored.to_cpu();
+    assert_eq!(restored.memory[..], cpu.memory[..]);
+    assert_eq!(restored.int_reg, cpu.int_reg);
+    assert_eq!(restored.pc, 0x102);
+    assert_eq!(restored.history, cpu.history);
+}
+
+#[test]
+fn symbolised_backtrace() {
+    let mut cpu = crate::CPU::new();
+    cpu.starts_at = 0x100;
+    cpu.memory[0x100] = 0x8110;
+    cpu.memory[0x110] = 0x8120;
+    cpu.bp = 0x50;
+    cpu.sp = 0x4D;
+    cpu.memory[0x4D..0x50].copy_from_slice(&[7, 0x110, 0x100]);
+    assert_eq!(crate::backtrace(&cpu), vec![0x110, 0x100]);
+
+    let table =
+        crate::SymbolTable::parse("label main 0x0100\nlabel sub 0x0110\nline 0x0112 9 lib.asm\n");
+    assert_eq!(table.symbolize(0x110).as_deref(), Some("sub"));
+    assert_eq!(table.symbolize(0x10F).as_deref(), Some("main+15"));
+    assert_eq!(table.symbolize(0x50), None);
+    assert_eq!(table.describe(0x112), "x0112 <sub+2> (lib.asm:9)");
+}
+
+#[test]
+fn interrupt_pauses_run() {
+    use std::sync::{atomic::Ordering, Arc};
+    // a loop that never halts on its own
+    let mut bdb = bdb_with_program("jmp [256]");
+    bdb.dbgcpu.pushret = false;
+
+    let interrupted = Arc::clone(&bdb.interrupted);
+    let executing = Arc::clone(&bdb.executing);
+    let interrupter = std::thread::spawn(move || {
+        while !executing.load(Ordering::SeqCst) {
+            std::thread::yield_now();
+        }
+        interrupted.store(true, Ordering::SeqCst);
+    });
+    bdb.run_until(None);
+    interrupter.join().unwrap();
+
+    assert!(bdb.dbgcpu.running);
+    assert!(!bdb.dbgcpu.err);
+    assert_eq!(bdb.dbgcpu.pc, 0x100);
+    assert!(!bdb.executing.load(Ordering::SeqCst));
+    assert!(!bdb.interrupted.load(Ordering::SeqCst));
+}
+
+#[test]
+fn tui_layout_helpers() {
+    use bdb_tui::{fit, pane, strip_ansi};
+    let colored = "\x1B[1;32mpc\x1B[0m: x0100";
+    assert_eq!(strip_ansi(colored), "pc: x0100");
+    assert_eq!(strip_ansi("\x1B[2Kplain"), "plain");
+
+    // padding keeps the colours, truncation drops them
+    assert_eq!(fit(colored, 12), format!("{colored}   "));
+    assert_eq!(fit(colored, 4), "pc: ");
+    assert_eq!(fit("", 3), "   ");
+
+    let lines = vec!["one".to_string(), "a line too long to fit".to_string()];
+    let rows = pane("Stack", &lines, 14, 5);
+    assert_eq!(rows.len(), 5);
+    for row in &rows {
+        assert_eq!(strip_ansi(row).chars().count(), 14, "{row:?}");
+    }
+    assert_eq!(strip_ansi(&rows[0]), "╭─ Stack ────╮");
+    assert_eq!(rows[1], "│ one        │");
+    assert_eq!(rows[2], "│ a line too │");
+    assert_eq!(rows[3], "│            │");
+    assert_eq!(rows[4], "╰────────────╯");
+}
+
+#[test]
+fn memory_search_and_ranges() {
+    use crate::MEMORY_SIZE;
+    use bdb_mem::{find_words, parse_quoted};
+    assert_eq!(
+        parse_quoted(r#"say \"hi\"" 0 10"#),
+        Some((r#"say "hi""#.to_string(), " 0 10"))
+    );
+    assert_eq!(parse_quoted(r"a\n\0b"), None);
+    assert_eq!(parse_quoted(r#"trailing \"#), None);
+
+    let mut memory = vec![0; MEMORY_SIZE];
+    memory[MEMORY_SIZE - 1] = 7;
+    assert_eq!(
+        find_words(&memory, &[7], 0, MEMORY_SIZE - 1),
+        vec![MEMORY_SIZE - 1]
+    );
+    memory[MEMORY_SIZE - 2] = 6;
+    assert_eq!(
+        find_words(&memory, &[6, 7], 0xFF00, MEMORY_SIZE - 1),
+        vec![MEMORY_SIZE - 2]
+    );
+    assert!(find_words(&memory, &[7, 7], 0, MEMORY_SIZE - 1).is_empty());
+
+    let mut bdb = BDB::new("missing.bin").unwrap();
+    bdb.dbgcpu.pc = 4;
+    assert_eq!(bdb.disassembly_range(""), Ok(0..=12));
+    bdb.dbgcpu.pc = 0xFFFE;
+    assert_eq!(bdb.disassembly_range(""), Ok(0xFFF6..=0xFFFF));
+    assert_eq!(bdb.disassembly_range("0x10 0x20"), Ok(0x10..=0x20));
+    assert!(bdb.disassembly_range("0x20 0x10").is_err());
+    assert!(bdb.disassembly_range("0x10").is_err());
+
+    // a count past the end of memory stops at the last word
+    bdb.handle_examine("x/18446744073709551615", "0");
+}
+
+#[cfg(test)]
+fn bdb_with_program(source: &str) -> BDB {
+    let mut bdb = BDB::new("missing.bin").unwrap();
+    for (offset, word) in assemble(source).unwrap().into_iter().enumerate() {
+        bdb.dbgcpu.memory[0x100 + offset] = word;
+    }
+    bdb.dbgcpu.pc = 0x100;
+    bdb
+}
+
+#[test]
+fn run_until_and_execute() {
+    let program = "add r0, 1\nadd r0, 1\nadd r0, 1\nhlt";
+    let mut bdb = bdb_with_program(program);
+    bdb.run_until(Some(0x102));
+    assert_eq!(bdb.dbgcpu.pc, 0x102);
+    assert_eq!(bdb.dbgcpu.int_reg[0], 2);
+
+    // starting on the target runs until it comes back around
+    let mut bdb = bdb_with_program("add r0, 1\njmp [256]");
+    bdb.dbgcpu.pushret = false;
+    bdb.run_until(Some(0x100));
+    assert_eq!(bdb.dbgcpu.pc, 0x100);
+    assert_eq!(bdb.dbgcpu.int_reg[0], 1);
+
+    let mut bdb = bdb_with_program(program);
+    bdb.handle_execute("2");
+    assert_eq!((bdb.dbgcpu.pc, bdb.dbgcpu.int_reg[0]), (0x102, 2));
+    bdb.handle_execute("10");
+    assert_eq!((bdb.dbgcpu.pc, bdb.dbgcpu.int_reg[0]), (0x103, 3));
+    assert!(!bdb.dbgcpu.running);
+
+    let mut bdb = bdb_with_program(program);
+    bdb.breakpoints.push(0x101);
+    bdb.handle_execute("3");
+    assert_eq!(bdb.dbgcpu.pc, 0x101);
+}
+
+#[test]
+fn set_registers_flags_and_memory() {
+    let mut bdb = bdb_with_program("hlt");
+    bdb.handle_set("r0 0x10");
+    bdb.handle_set("r1 -1");
+    bdb.handle_set("r2 0b101");
+    bdb.handle_set("r6 1.5");
+    bdb.handle_set("sp 0x200");
+    bdb.handle_set("z on");
+    bdb.handle_set("pushret 0");
+    bdb.handle_set("[0x300] 7");
+    assert_eq!(bdb.dbgcpu.int_reg[..3], [0x10, 0xFFFF, 5]);
+    assert_eq!(bdb.dbgcpu.float_reg[0], 1.5);
+    assert_eq!(bdb.dbgcpu.sp, 0x200);
+    assert!(bdb.dbgcpu.zflag);
+    assert!(!bdb.dbgcpu.pushret);
+    assert_eq!(bdb.dbgcpu.memory[0x300], 7);
+
+    // values parse_number cannot fit leave everything untouched
+    bdb.handle_set("r0 70000");
+    bdb.handle_set("r0 -40000");
+    bdb.handle_set("pc 0x10000");
+    bdb.handle_set("z maybe");
+    bdb.handle_set("[0x10000] 1");
+    bdb.handle_set("[0x300] 0x1FFFF");
+    bdb.handle_set("r9 1");
+    assert_eq!(bdb.dbgcpu.int_reg[0], 0x10);
+    assert_eq!(bdb.dbgcpu.pc, 0x100);
+    assert!(bdb.dbgcpu.zflag);
+    assert_eq!(bdb.dbgcpu.memory[0x300], 7);
+}
+
+#[test]
+fn assemble_patch_labels() {
+    // a label declared by one 'asm' is gone by the next
+    let mut bdb = bdb_with_program("hlt");
+    bdb.handle_assemble("0x100 spin: jmp spin");
+    bdb.handle_assemble("0x101 spin: jmp spin");
+    assert_eq!(bdb.patches.len(), 2);
+    assert_eq!(bdb.dbgcpu.memory[0x100], assemble("jmp [256]").unwrap()[0]);
+    assert_eq!(bdb.dbgcpu.memory[0x101], assemble("jmp [257]").unwrap()[0]);
+    bdb.handle_assemble("0x102 jmp spin");
+    assert_eq!(bdb.patches.len(), 2);
+
+    // labels from the ROM's symbol file can be jumped to
+    bdb.symbols = crate::SymbolTable::parse("label main 0x0200\n");
+    bdb.handle_assemble("0x102 jmp main");
+    assert_eq!(bdb.patches.len(), 3);
+    assert_eq!(bdb.dbgcpu.memory[0x102], assemble("jmp [512]").unwrap()[0]);
+}
+
+#[test]
+fn symbol_file_records() {
+    // written by 'basm -s' for a two-routine program
+    let table = crate::SymbolTable::parse(
+        "; basm symbol file\n\
+         label main 0x0100\n\
+         label sum 0x0103\n\
+         line 0x0100 3 main.asm\n\
+         line 0x0101 4 main.asm\n\
+         line 0x0102 5 main.asm\n\
+         line 0x0103 7 main.asm\n\
+         line 0x0104 8 main.asm\n",
+    );
+    assert_eq!(table.labels.len(), 2);
+    assert_eq!(table.lookup("sum"), Some(0x103));
+    assert_eq!(table.lines.len(), 5);
+    assert_eq!(table.source(0x104).unwrap().to_string(), "main.asm:8");
+    assert_eq!(table.describe(0x104), "x0104 <sum+1> (main.asm:8)");
+
+    // malformed records are skipped, the rest of the file still loads
+    let table = crate::SymbolTable::parse(
+        "label\nlabel lonely\nlabel bad 0xZZ\nline 0x0100 three main.asm\n\
+         line 0x0100 3\nsymbol main 0x0100\n  label ok 0x0200  \n",
+    );
+    assert_eq!(table.labels.len(), 1);
+    assert_eq!(table.lookup("ok"), Some(0x200));
+    assert!(table.lines.is_empty());
+
+    // a name given twice keeps the later address
+    let table = crate::SymbolTable::parse("label twice 0x0100\nlabel twice 0x0200\n");
+    assert_eq!(table.lookup("twice"), Some(0x200));
+    assert_eq!(table.name_at(0x100), None);
+}
+
+#[test]
+fn display_expressions() {
+    let mut bdb = bdb_with_program("hlt");
+    bdb.symbols = crate::SymbolTable::parse("label count 0x0200\nlabel msg 0x0210\n");
+    bdb.dbgcpu.int_reg[2] = 42;
+    bdb.dbgcpu.float_reg[1] = 2.5;
+    bdb.dbgcpu.sp = 0x50;
+    bdb.dbgcpu.memory[0x200] = 0x1F;
+    bdb.dbgcpu.memory[0x300] = 9;
+    for (offset, c) in "hi\n".chars().enumerate() {
+        bdb.dbgcpu.memory[0x210 + offset] = c as u16;
+    }
+
+    let eval = |expr: &str| bdb.evaluate_display(expr);
+    assert_eq!(eval("r2"), Ok("42".to_string()));
+    assert_eq!(eval("R7"), Ok("2.5".to_string()));
+    assert_eq!(eval("pc"), Ok("x0100".to_string()));
+    assert_eq!(eval("sp"), Ok("x0050".to_string()));
+    assert_eq!(eval("count"), Ok("31 (001F)".to_string()));
+    assert_eq!(eval("[count]"), Ok("31 (001F)".to_string()));
+    assert_eq!(eval("[0x300]"), Ok("9 (0009)".to_string()));
+    assert_eq!(eval("str msg"), Ok("\"hi\\n\"".to_string()));
+    assert_eq!(eval("str 0x300"), Ok("\"\\t\"".to_string()));
+    assert!(eval("missing").is_err());
+    assert!(eval("[missing]").is_err());
+    assert!(eval("str").is_err());
+
+    bdb.handle_display("[nowhere]");
+    assert!(bdb.displays.is_empty());
+    bdb.handle_display("r2");
+    assert_eq!(bdb.displays, ["r2"]);
+}
+
+#[cfg(test)]
+fn lockstep_with(primary: &str, other: &str) -> BDB {
+    let mut bdb = bdb_with_program(primary);
+    let mut cpu = bdb_with_program(other).dbgcpu;
+    cpu.pc = 0x100;
+    bdb.lockstep = Some(Lockstep {
+        exe: "other.bin".to_string(),
+        cpu,
+    });
+    bdb
+}
+
+#[test]
+fn lockstep_divergence() {
+    let mut bdb = lockstep_with("add r1, 1\nadd r0, 1\nhlt", "add r1, 1\nadd r0, 2\nhlt");
+    assert_eq!(bdb.step_lockstep(None), ["r0"]);
+    assert_eq!(bdb.dbgcpu.pc, 0x102);
+
+    // differing code is not a divergence until it stores something different
+    let mut bdb = lockstep_with(
+        "add r1, 5\nst [40], r1\nst [42], r1\nhlt",
+        "add r1, 5\nst [41], r1\nst [42], r1\nhlt",
+    );
+    assert_eq!(bdb.step_lockstep(Some(1)), Vec::<String>::new());
+    assert_eq!(bdb.step_lockstep(None), ["x0028", "x0029"]);
+    assert_eq!(bdb.dbgcpu.pc, 0x102);
+    assert!(bdb.dbgcpu.writes.is_none());
+
+    let mut bdb = lockstep_with("push 3\npop r0\nhlt", "push 3\npop r0\nhlt");
+    assert!(bdb.step_lockstep(None).is_empty());
+    assert!(!bdb.dbgcpu.running);
+}
@@ -0,0 +1,85 @@
+use crate::*;
+use std::{error::Error, fmt, io};
+
+/// Why a subroutine called from the host did not return
+#[derive(Debug)]
+pub enum CallError {
+    UnknownLabel(String),
+    Crashed(UnrecoverableError),
+    Halted(u16),
+    CycleLimit(u64),
+}
+
+impl Error for CallError {}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::UnknownLabel(name) => write!(f, "no label named '{name}'"),
+            CallError::Crashed(e) => write!(f, "{e}"),
+            CallError::Halted(addr) => write!(f, "halted at x{addr:04X} before returning"),
+            CallError::CycleLimit(n) => write!(f, "did not return within {n} cycles"),
+        }
+    }
+}
+
+impl CPU {
+    /// A CPU with a ROM file loaded, ready to run or to call into. Nothing
+    /// from the command line applies, so it can be used from any test.
+    pub fn from_rom(path: &str) -> io::Result<CPU> {
+        let mut cpu = CPU::new();
+        cpu.load_rom_with(&create_rom(path)?, &LoadOptions::default())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        Ok(cpu)
+    }
+
+    /// Calls the subroutine at `addr` as a `JMP` with pushret set would, and runs
+    /// until its matching `RET`. Returns the number of cycles it took; the
+    /// registers and memory are left as the subroutine left them.
+    pub fn call(&mut self, addr: u16, limit: u64) -> Result<u64, CallError> {
+        // the return address is popped back off by the subroutine's RET
+        let caller_sp = self.sp;
+        let site = self.pc;
+        // r8 reads as pc, which a literal cannot hold from x8000 up
+        self.handle_push(&Argument::Register(8))
+            .map_err(CallError::Crashed)?;
+        self.pc = addr;
+        self.note_call(site);
+        self.running = true;
+
+        let mut cycles = 0;
+        while cycles < limit {
+            cycles += 1;
+            self.ir = self.memory[self.pc as usize] as i16;
+            let parsed_ins = self.decode_instruction();
+            if let Err(e) = self.execute_instruction(&parsed_ins) {
+                self.err = true;
+                self.errmsg = e.kind().to_string();
+                self.running = false;
+                return Err(CallError::Crashed(e));
+            }
+            if !self.running {
+                return Err(CallError::Halted(self.pc));
+            }
+            if matches!(parsed_ins, Instruction::RET) && self.sp == caller_sp {
+                self.running = false;
+                return Ok(cycles);
+            }
+        }
+        self.running = false;
+        Err(CallError::CycleLimit(limit))
+    }
+
+    /// Calls the subroutine at a label from a `basm -s` symbol file
+    pub fn call_label(
+        &mut self,
+        symbols: &SymbolTable,
+        label: &str,
+        limit: u64,
+    ) -> Result<u64, CallError> {
+        let addr = symbols
+            .lookup(label)
+            .ok_or_else(|| CallError::UnknownLabel(label.to_string()))?;
+        self.call(addr, limit)
+    }
+}
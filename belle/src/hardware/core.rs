@@ -1,7 +1,8 @@
 use crate::{config::CONFIG, interrupt::*, Argument::*, Instruction::*, *};
 use colored::Colorize;
-use std::{thread, time::Duration};
+use std::{collections::VecDeque, thread, time::Duration};
 pub const MEMORY_SIZE: usize = 65536;
+pub const HISTORY_LENGTH: usize = 32;
 
 #[cfg(feature = "window")]
 use fontdue::{Font, FontSettings};
@@ -43,6 +44,14 @@ pub struct CPU {
     pub pmem: bool,
     pub pushret: bool,
     pub fuzz: bool,
+    pub history: VecDeque<(u16, u16)>, // address and word of recently executed instructions
+    pub input: Option<VecDeque<u8>>,   // scripted stdin for INT 9 and INT 40
+    pub output: Option<String>,        // captured stdout of the print interrupts
+    pub strict: bool,                  // reject words that basm would never produce
+    pub shadow: Option<ShadowMemory>,  // which words have been written, when checking reads
+    pub call_stack: Option<ShadowStack>, // which stack slots hold return addresses, when checking calls
+    pub writes: Option<Vec<u16>>,        // addresses stored to, while a debugger is tracking them
+    pub warning_handler: Option<WarningHandler>, // where recoverable errors go; dropped when None
 }
 
 impl Default for CPU {
@@ -76,6 +85,14 @@ impl CPU {
             errmsg: String::from(""),
             pmem: false,
             fuzz: false,
+            history: VecDeque::new(),
+            input: None,
+            output: None,
+            strict: false,
+            shadow: None,
+            call_stack: None,
+            writes: None,
+            warning_handler: None,
         }
     }
     pub fn run(&mut self) -> PossibleCrash {
@@ -83,6 +100,7 @@ impl CPU {
         if self.do_not_run {
             return Ok(());
         }
+        #[allow(unused_mut)] // only reassigned with the window feature
         let mut can_make_window = false;
         #[cfg(feature = "window")]
         let mut window_attempt = None;
@@ -131,6 +149,11 @@ impl CPU {
             thread::spawn(move || {
                 let mut cycles = 0;
                 let starting = std::time::Instant::now();
+                let profile_out = CONFIG.profile_output();
+                let mut profiler = profile_out
+                    .is_some()
+                    .then(|| Profiler::new(self_clone.starts_at));
+                let mut coverage = CONFIG.coverage.is_some().then(Coverage::new);
                 while self_clone.running {
                     cycles += 1;
                     if delay != 0 {
@@ -139,10 +162,21 @@ impl CPU {
                     self_clone.ir = self_clone.memory[self_clone.pc as usize] as i16;
 
                     let parsed_ins = self_clone.decode_instruction();
+                    let (pc, pushret) = (self_clone.pc, self_clone.pushret);
+                    if CONFIG.write {
+                        self_clone.record_history();
+                    }
                     if let Err(e) = self_clone.execute_instruction(&parsed_ins) {
                         self_clone.err = true;
-                        self_clone.errmsg = e.only_err().to_string();
+                        self_clone.errmsg = e.kind().to_string();
                         self_clone.running = false;
+                        if let (Some(profiler), Some(path)) = (&profiler, &profile_out) {
+                            profiler.finish(&self_clone, &CONFIG.rom, path);
+                        }
+                        if let Some(coverage) = coverage.as_mut() {
+                            coverage.record(pc, &parsed_ins, &self_clone);
+                            coverage.finish(&self_clone);
+                        }
                         #[cfg(feature = "window")]
                         if can_make_window {
                             let _ = tx.send(None);
@@ -150,7 +184,14 @@ impl CPU {
                         if CONFIG.verbose {
                             println!("{}", self_clone);
                         }
-                        return Err(e);
+                        return (self_clone, Err(e));
+                    }
+
+                    if let Some(profiler) = profiler.as_mut() {
+                        profiler.record(pc, pushret, &parsed_ins, &self_clone);
+                    }
+                    if let Some(coverage) = coverage.as_mut() {
+                        coverage.record(pc, &parsed_ins, &self_clone);
                     }
 
                     if CONFIG.verbose {
@@ -187,11 +228,17 @@ impl CPU {
                         format_with_commas(cycles)
                     );
                 }
+                if let (Some(profiler), Some(path)) = (&profiler, &profile_out) {
+                    profiler.finish(&self_clone, &CONFIG.rom, path);
+                }
+                if let Some(coverage) = &coverage {
+                    coverage.finish(&self_clone);
+                }
                 if CONFIG.pretty {
                     self_clone.pmem = !CONFIG.no_print_memory;
                     println!("{self_clone}");
                 }
-                Ok(())
+                (self_clone, Ok(()))
             })
         };
 
@@ -263,11 +310,44 @@ impl CPU {
             print_b();
         }
 
-        execution_handle.join().unwrap()?;
-        Ok(())
+        // keep the final state so crash dumps show where the program stopped
+        let (cpu, result) = execution_handle.join().unwrap();
+        *self = cpu;
+        result
+    }
+
+    /// Remembers the instruction at the program counter, keeping the last `HISTORY_LENGTH`
+    pub fn record_history(&mut self) {
+        if self.history.len() == HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history
+            .push_back((self.pc, self.memory[self.pc as usize]));
+    }
+
+    /// Stores a word on behalf of an instruction, noting the address if writes are tracked
+    pub fn write_memory(&mut self, addr: usize, value: u16) {
+        self.memory[addr] = value;
+        if let Some(writes) = self.writes.as_mut() {
+            writes.push(addr as u16);
+        }
     }
 
     pub fn execute_instruction(&mut self, ins: &Instruction) -> PossibleCrash {
+        if self.strict {
+            if let Err(why) = Self::check_encoding(self.ir as u16) {
+                self.running = false;
+                self.err = true;
+                return Err(UnrecoverableError::IllegalInstruction(
+                    self.ir,
+                    self.pc,
+                    Some(format!(
+                        "the word {:04X} is not a valid instruction: {why}",
+                        self.ir as u16
+                    )),
+                ));
+            }
+        }
         match ins {
             HLT => self.running = false,
             ADD(arg1, arg2) => self.handle_add(arg1, arg2)?,
@@ -297,7 +377,7 @@ impl CPU {
     pub fn set_register_value(&mut self, arg: &Argument, value: f64) -> PossibleCrash {
         if let Register(n) = arg {
             if let Err(e) = self.check_overflow(value as i64, *n as u16) {
-                eprint!("{e}");
+                self.warn(e);
                 return Ok(());
             }
             match *n {
@@ -309,7 +389,7 @@ impl CPU {
                 _ => self.int_reg[*n as usize] = value as u16,
             }
             if let Err(e) = self.check_overflow(value as i64, *n as u16) {
-                eprint!("{e}");
+                self.warn(e);
             }
         }
         Ok(())
@@ -324,7 +404,7 @@ fn format_with_commas(n: usize) -> String {
     let len = s.len();
 
     for i in (1..len).rev() {
-        if (len - i) % 3 == 0 {
+        if (len - i).is_multiple_of(3) {
             s.insert(i, ',');
         }
     }
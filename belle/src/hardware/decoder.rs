@@ -88,6 +88,47 @@ impl fmt::Display for Instruction {
     }
 }
 
+impl Instruction {
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::HLT => "HLT",
+            Instruction::ADD(_, _) => "ADD",
+            Instruction::BO(_) => "BO",
+            Instruction::POP(_) => "POP",
+            Instruction::DIV(_, _) => "DIV",
+            Instruction::RET => "RET",
+            Instruction::LD(_, _) => "LD",
+            Instruction::ST(_, _) => "ST",
+            Instruction::JMP(_) => "JMP",
+            Instruction::BNZ(_) => "BNZ",
+            Instruction::BL(_) => "BL",
+            Instruction::BG(_) => "BG",
+            Instruction::BNO(_) => "BNO",
+            Instruction::BZ(_) => "BZ",
+            Instruction::CMP(_, _) => "CMP",
+            Instruction::NAND(_, _) => "NAND",
+            Instruction::PUSH(_) => "PUSH",
+            Instruction::INT(_) => "INT",
+            Instruction::MOV(_, _) => "MOV",
+            Instruction::LEA(_, _) => "LEA",
+        }
+    }
+
+    // a jump or branch, which pushes a return address when pushret is set
+    pub fn is_jump(&self) -> bool {
+        matches!(
+            self,
+            Instruction::JMP(_)
+                | Instruction::BO(_)
+                | Instruction::BNO(_)
+                | Instruction::BZ(_)
+                | Instruction::BNZ(_)
+                | Instruction::BL(_)
+                | Instruction::BG(_)
+        )
+    }
+}
+
 impl Argument {
     pub fn is_ptr(&self) -> bool {
         matches!(self, Argument::RegPtr(_) | Argument::MemPtr(_))
@@ -108,6 +149,7 @@ impl CPU {
             },
             Literal(n) => Ok((*n) as f32),
             MemPtr(n) => {
+                self.check_initialised(*n as usize)?;
                 let tmp = self.memory[*n as usize] as usize;
                 if tmp > MEMORY_SIZE {
                     self.err = true;
@@ -117,6 +159,7 @@ impl CPU {
                         Some("Segmentation fault whilst processing pointer.\nMemory address invalid (too large).".to_string()),
                     ));
                 }
+                self.check_initialised(tmp)?;
                 Ok(self.memory[tmp] as f32)
             }
             RegPtr(n) => {
@@ -137,14 +180,23 @@ impl CPU {
                     return Err(self
                         .generate_segfault("Segmentation fault handling pointer.\nAddress OOB."));
                 }
+                self.check_initialised(memloc)?;
                 Ok(self.memory[memloc] as f32)
             }
-            MemAddr(n) => Ok(self.memory[*n as usize] as f32),
+            MemAddr(n) => {
+                self.check_initialised(*n as usize)?;
+                Ok(self.memory[*n as usize] as f32)
+            }
         }
     }
 
     pub fn decode_instruction(&self) -> Instruction {
-        let ir = self.ir;
+        Self::decode_word(self.ir as u16)
+    }
+
+    /// Decodes an arbitrary memory word without touching the instruction register
+    pub fn decode_word(word: u16) -> Instruction {
+        let ir = word as i16;
         let opcode = (ir >> 12) & 0b1111;
         let mut ins_type = if ((ir >> 8) & 1) == 1 {
             1
@@ -275,9 +327,104 @@ impl CPU {
                 let part = ir & 0b111111111;
                 LEA(Register(destination), MemAddr(part))
             }
-            _ => unsafe {
-                std::hint::unreachable_unchecked();
-            },
+            _ => unreachable!("the opcode is four bits"),
         }
     }
+
+    /// Checks a word against the encodings basm produces, explaining which
+    /// bits make it invalid. `decode_word` ignores these bits instead.
+    pub fn check_encoding(word: u16) -> Result<(), String> {
+        let ir = word as i16;
+        let opcode = (ir >> 12) & 0b1111;
+        let name = Self::decode_word(word).mnemonic();
+        // the last four bits name a register, of which there are ten
+        let register = |what: &str| {
+            let n = word & 0b1111;
+            if n > 9 {
+                Err(format!("{name} {what} r{n}, but only r0 to r9 exist"))
+            } else {
+                Ok(())
+            }
+        };
+        match opcode {
+            HLT_OP => unused(word, 0b1111_1111_1111, "HLT takes no operands"),
+            ADD_OP | DIV_OP | CMP_OP | NAND_OP | MOV_OP => {
+                // literals and memory pointers use every remaining bit
+                if word & (0b11 << 7) != 0 {
+                    Ok(())
+                } else if word & (1 << 6) != 0 {
+                    unused(word, 0b11_0000, &format!("{name} with a register pointer"))?;
+                    register("points at")
+                } else {
+                    unused(word, 0b111_0000, &format!("{name} with a register"))?;
+                    register("reads")
+                }
+            }
+            PUSH_OP => {
+                unused(word, 0b1110_0000_0000, "PUSH has no destination")?;
+                if word & (1 << 8) != 0 {
+                    Ok(())
+                } else {
+                    unused(word, 0b1111_0000, "PUSH takes a literal or a register")?;
+                    register("reads")
+                }
+            }
+            INT_OP => {
+                unused(word, 0b1110_0000_0000, "INT has no destination")?;
+                if word & (1 << 8) == 0 {
+                    Err("INT takes a literal, but the literal bit (8) is clear".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+            POP_OP => {
+                if word & (1 << 11) != 0 {
+                    Ok(())
+                } else {
+                    unused(word, 0b111_1111_0000, "POP into a register")?;
+                    register("writes")
+                }
+            }
+            ST_OP => {
+                if word & (1 << 11) != 0 {
+                    unused(word, 0b100_0111_1000, "ST through a register pointer")
+                } else {
+                    Ok(())
+                }
+            }
+            JMP_OP | BO_OP | BZ_OP | RET_OP => {
+                if opcode == JMP_OP {
+                    unused(word, 1 << 11, "JMP has no inverted form")?;
+                }
+                if opcode == RET_OP && word & 0b1111_1111_1111 == 0 {
+                    return Ok(());
+                }
+                if word & (1 << 10) != 0 {
+                    unused(word, 0b11_1111_0000, &format!("{name} through a register"))?;
+                    register("jumps through")
+                } else {
+                    Ok(())
+                }
+            }
+            // every bit of LD and LEA is the destination or the address
+            _ => Ok(()),
+        }
+    }
+}
+
+// fails when any of the bits in mask are set in word
+fn unused(word: u16, mask: u16, what: &str) -> Result<(), String> {
+    let set: Vec<String> = (0..16)
+        .rev()
+        .filter(|bit| word & mask & (1 << bit) != 0)
+        .map(|bit| bit.to_string())
+        .collect();
+    match set.as_slice() {
+        [] => Ok(()),
+        [bit] => Err(format!("{what}, so bit {bit} must be clear")),
+        [rest @ .., last] => Err(format!(
+            "{what}, so bits {} and {last} must be clear",
+            rest.join(", ")
+        )),
+    }
 }
@@ -1,7 +1,8 @@
-use crate::{config::CONFIG, *};
-use colored::*;
+use crate::*;
 use std::fmt;
 
+/// Why the CPU stopped. Each carries the instruction register, the program
+/// counter and an optional message.
 #[derive(Debug)]
 pub enum UnrecoverableError {
     SegmentationFault(i16, u16, Option<String>),
@@ -12,23 +13,40 @@ pub enum UnrecoverableError {
     StackUnderflow(i16, u16, Option<String>),
     ReadFail(i16, u16, Option<String>),
     WindowFail(i16, u16, Option<String>), // IR, PC, MSG
+    UninitialisedRead(i16, u16, Option<String>),
+    CallStackMismatch(i16, u16, Option<String>),
 }
 
+/// Something worth knowing about that the CPU carries on after. Each carries
+/// the program counter and an optional message.
 #[derive(Debug)]
 pub enum RecoverableError {
     Overflow(u16, Option<String>),
     BackwardStack(u16, Option<String>),
+    UninitialisedRead(u16, Option<String>),
+    CallStackMismatch(u16, Option<String>),
 }
 
 pub type PossibleWarn = Result<(), RecoverableError>;
 pub type PossibleCrash = Result<(), UnrecoverableError>;
 
+/// Receives each recoverable error of a CPU as it happens
+pub type WarningHandler = fn(&RecoverableError);
+
 impl std::error::Error for UnrecoverableError {}
 impl std::error::Error for RecoverableError {}
 impl UnrecoverableError {
-    pub fn only_err(&self) -> String {
-        let (_, err_type, _, _) = self.details();
-        format!("{} {}", "UNRECOVERABLE ERROR:".red(), err_type.bold().red())
+    pub fn kind(&self) -> &str {
+        self.details().1
+    }
+    pub fn ir(&self) -> i16 {
+        self.details().0
+    }
+    pub fn location(&self) -> u16 {
+        self.details().2
+    }
+    pub fn message(&self) -> Option<&str> {
+        self.details().3.as_deref()
     }
     fn details(&self) -> (i16, &str, u16, &Option<String>) {
         match self {
@@ -46,50 +64,49 @@ impl UnrecoverableError {
             UnrecoverableError::StackUnderflow(ir, loc, msg) => (*ir, "Stack underflow", *loc, msg),
             UnrecoverableError::ReadFail(ir, loc, msg) => (*ir, "Read fail", *loc, msg),
             UnrecoverableError::WindowFail(ir, loc, msg) => (*ir, "Window fail", *loc, msg),
+            UnrecoverableError::UninitialisedRead(ir, loc, msg) => {
+                (*ir, "Uninitialised read", *loc, msg)
+            }
+            UnrecoverableError::CallStackMismatch(ir, loc, msg) => {
+                (*ir, "Call stack mismatch", *loc, msg)
+            }
         }
     }
 }
 
 impl RecoverableError {
+    pub fn kind(&self) -> &str {
+        self.details().0
+    }
+    pub fn location(&self) -> u16 {
+        self.details().1
+    }
+    pub fn message(&self) -> Option<&str> {
+        self.details().2.as_deref()
+    }
+    /// Whether the warning comes from a check the user asked for, rather
+    /// than from the program's normal running
+    pub fn is_check(&self) -> bool {
+        matches!(
+            self,
+            RecoverableError::UninitialisedRead(..) | RecoverableError::CallStackMismatch(..)
+        )
+    }
     fn details(&self) -> (&str, u16, &Option<String>) {
         match self {
             RecoverableError::Overflow(loc, msg) => ("Overflow", *loc, msg),
             RecoverableError::BackwardStack(loc, msg) => ("Backwards stack", *loc, msg),
+            RecoverableError::UninitialisedRead(loc, msg) => ("Uninitialised read", *loc, msg),
+            RecoverableError::CallStackMismatch(loc, msg) => ("Call stack mismatch", *loc, msg),
         }
     }
 }
 
 impl fmt::Display for UnrecoverableError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let (ir, err_type, location, msg) = self.details();
-        write!(f, "{} ", "UNRECOVERABLE ERROR:".red())?;
-        write!(f, "{}", err_type.bold().red())?;
-
-        if let Some(s) = msg {
-            if CONFIG.debug || CONFIG.verbose {
-                write!(f, "\n{}", s.yellow())?;
-            }
-        }
-        if CONFIG.debug || CONFIG.verbose {
-            let line = "─".repeat(12);
-            writeln!(f, "\n╭{}──────────{}─{}────╮", line, line, line)?;
-            let mut cpu = CPU::new();
-            cpu.ir = ir;
-            write!(f, "│{}:", " Instruction".bold())?;
-            write!(f, " {}", cpu.decode_instruction().to_string().bold())?;
-            let inslen = 52
-                - "│ Instruction".len()
-                - cpu.decode_instruction().to_string().trim().len()
-                - " Address: ".len()
-                - location.to_string().len();
-            write!(
-                f,
-                " {}: {}",
-                "Address".blue().bold(),
-                location.to_string().bold()
-            )?;
-            writeln!(f, "{}│", " ".repeat(inslen))?;
-            writeln!(f, "╰{}──────────{}─{}────╯", line, line, line)?;
+        write!(f, "{} at x{:04X}", self.kind(), self.location())?;
+        if let Some(message) = self.message() {
+            write!(f, ": {message}")?;
         }
         Ok(())
     }
@@ -97,26 +114,22 @@ impl fmt::Display for UnrecoverableError {
 
 impl fmt::Display for RecoverableError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if CONFIG.compact_print || (!CONFIG.debug && !CONFIG.verbose) {
-            return Ok(());
-        }
-        let (err_type, location, msg) = self.details();
-        write!(f, "{}: ", "RECOVERABLE ERROR".yellow())?;
-        write!(f, "{}", err_type.yellow())?;
-
-        if let Some(s) = msg {
-            if CONFIG.debug || (CONFIG.verbose && !CONFIG.compact_print) {
-                write!(f, ": {}", s.magenta())?;
-            }
-        }
-        if CONFIG.debug || (CONFIG.verbose && !CONFIG.compact_print) {
-            writeln!(f, " at memory address {}", location.to_string().green())?;
+        write!(f, "{} at x{:04X}", self.kind(), self.location())?;
+        if let Some(message) = self.message() {
+            write!(f, ": {message}")?;
         }
         Ok(())
     }
 }
 
 impl CPU {
+    /// Passes a recoverable error to this CPU's warning handler, if it has one
+    pub fn warn(&self, warning: RecoverableError) {
+        if let Some(handler) = self.warning_handler {
+            handler(&warning);
+        }
+    }
+
     pub fn generate_invalid_register(&mut self) -> UnrecoverableError {
         self.running = false;
         self.err = true;
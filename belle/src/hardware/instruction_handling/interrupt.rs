@@ -2,6 +2,10 @@ use crate::UnrecoverableError::*;
 use crate::{config::CONFIG, *};
 use colored::*;
 use std::io::{self, Read, Write};
+
+/// What INT 9 puts in r0 once there is no more input
+pub const END_OF_INPUT: u16 = 0xFFFF;
+
 impl CPU {
     pub fn handle_int(&mut self, arg: &Argument) -> PossibleCrash {
         if self.fuzz {
@@ -55,7 +59,7 @@ impl CPU {
                     println!("╰─────────╯");
                     print_t();
                 } else {
-                    println!("{}", self.int_reg[code as usize]);
+                    self.write_output(&format!("{}\n", self.int_reg[code as usize]));
                 }
             }
             6 => {
@@ -66,7 +70,7 @@ impl CPU {
                     println!("╰─────────╯");
                     print_t();
                 } else {
-                    println!("{}", self.float_reg[0]);
+                    self.write_output(&format!("{}\n", self.float_reg[0]));
                 }
             }
             7 => {
@@ -77,7 +81,7 @@ impl CPU {
                     println!("╰─────────╯");
                     print_t();
                 } else {
-                    println!("{}", self.float_reg[1]);
+                    self.write_output(&format!("{}\n", self.float_reg[1]));
                 }
             }
             8 => {
@@ -92,17 +96,18 @@ impl CPU {
                 }
 
                 for index in starting_point..end_point {
-                    if index as usize >= memory.len() {
+                    if index as usize >= self.memory.len() {
                         return Err(self.generate_segfault(
                             "Segmentation fault. Memory index out of bounds on interrupt call 8.",
                         ));
                     }
+                    self.check_initialised(index as usize)?;
                     let value = self.memory[index as usize];
                     if value != 0 {
                         if CONFIG.verbose || CONFIG.debug {
                             stringy = format!("{}{}", stringy, value as u8 as char);
                         } else {
-                            print!("{}", value as u8 as char);
+                            self.write_output(&(value as u8 as char).to_string());
                         }
                     }
                 }
@@ -157,13 +162,7 @@ impl CPU {
                     println!("│ Reading one character.. │");
                     println!("╰─────────────────────────╯\n");
                 }
-                use crossterm::terminal;
-                terminal::enable_raw_mode().unwrap();
-                let mut buffer = [0; 1];
-                let _ = io::stdin().read_exact(&mut buffer);
-                self.int_reg[0] = buffer[0] as u16;
-
-                terminal::disable_raw_mode().unwrap();
+                self.int_reg[0] = self.read_byte();
                 io::stdout().flush().expect("Failed to flush stdout");
                 print_t();
             }
@@ -184,24 +183,7 @@ impl CPU {
             33 => self.rflag = !self.rflag,
             40 => {
                 print_b();
-                loop {
-                    let mut input = String::new();
-                    match io::stdin().read_line(&mut input) {
-                        Ok(_) => match input.trim().parse::<u16>() {
-                            Ok(value) => {
-                                self.int_reg[0] = value;
-                                break;
-                            }
-                            Err(e) => {
-                                println!("{}", EmuError::ReadFail(e.to_string()));
-                            }
-                        },
-                        Err(e) => {
-                            println!("{}", EmuError::ReadFail(e.to_string()));
-                        }
-                    }
-                }
-
+                self.int_reg[0] = self.read_number()?;
                 print_t();
             }
             41 => self.sflag = true,
@@ -219,6 +201,75 @@ impl CPU {
     }
 }
 
+impl CPU {
+    // program output goes to stdout unless it is being captured
+    fn write_output(&mut self, text: &str) {
+        match &mut self.output {
+            Some(output) => output.push_str(text),
+            None => print!("{text}"),
+        }
+    }
+
+    // one byte for INT 9, or 0xFFFF once input has run out
+    fn read_byte(&mut self) -> u16 {
+        if let Some(input) = &mut self.input {
+            return input.pop_front().map_or(END_OF_INPUT, u16::from);
+        }
+        use crossterm::terminal;
+        // stdin may not be a terminal when it is piped in
+        let raw = terminal::enable_raw_mode().is_ok();
+        let mut buffer = [0; 1];
+        let byte = match io::stdin().read_exact(&mut buffer) {
+            Ok(()) => u16::from(buffer[0]),
+            Err(_) => END_OF_INPUT,
+        };
+        if raw {
+            let _ = terminal::disable_raw_mode();
+        }
+        byte
+    }
+
+    // one line for INT 40. Scripted input has nobody to ask again, so a line
+    // that isn't a number crashes just like running out of input does
+    fn read_number(&mut self) -> Result<u16, UnrecoverableError> {
+        let (ir, pc) = (self.ir, self.pc);
+        let end_of_input = || ReadFail(ir, pc, Some("Reached the end of the input".to_string()));
+        if let Some(input) = &mut self.input {
+            if input.is_empty() {
+                return Err(end_of_input());
+            }
+            let end = input
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(input.len(), |n| n + 1);
+            let line: Vec<u8> = input.drain(..end).collect();
+            let line = String::from_utf8_lossy(&line);
+            return line.trim().parse::<u16>().map_err(|e| {
+                ReadFail(
+                    ir,
+                    pc,
+                    Some(format!("Could not read '{}' as a number: {e}", line.trim())),
+                )
+            });
+        }
+        loop {
+            let mut input = String::new();
+            match io::stdin().read_line(&mut input) {
+                Ok(0) => return Err(end_of_input()),
+                Ok(_) => match input.trim().parse::<u16>() {
+                    Ok(value) => return Ok(value),
+                    Err(e) => {
+                        println!("{}", render_emu_error(&EmuError::ReadFail(e.to_string())));
+                    }
+                },
+                Err(e) => {
+                    println!("{}", render_emu_error(&EmuError::ReadFail(e.to_string())));
+                }
+            }
+        }
+    }
+}
+
 pub fn print_b() {
     if CONFIG.compact_print && CONFIG.verbose {
         println!("╰────────────────┴───────────┴───────────┴───────────┴───────────┴───────────┴───────────┴───────────┴───────────┴───────────┴───────────┴───────────┴─────╯");
@@ -83,6 +83,7 @@ impl CPU {
     }
 
     fn jmp(&mut self, arg: &Argument) -> PossibleCrash {
+        let site = self.pc;
         if self.pushret {
             self.handle_push(&Argument::Literal(self.pc.try_into().unwrap()))?;
         }
@@ -107,6 +108,9 @@ impl CPU {
             }
             self.pc = self.get_value(&Argument::Register(*n))? as u16;
         }
+        if self.pushret {
+            self.note_call(site);
+        }
         Ok(())
     }
 }
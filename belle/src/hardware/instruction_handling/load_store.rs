@@ -11,7 +11,8 @@ impl CPU {
                     Some("segmentation fault whilst storing to an address. OOB".to_string()),
                 ));
             }
-            self.memory[index] = source as u16;
+            self.write_memory(index, source as u16);
+            self.mark_initialised(index..index + 1);
         } else if let RegPtr(n) = arg1 {
             let addr = match self.get_value(&Register(*n)) {
                 Ok(a) => a as usize,
@@ -23,7 +24,8 @@ impl CPU {
                     self.ir, self.pc, None,
                 ));
             }
-            self.memory[addr] = source as u16;
+            self.write_memory(addr, source as u16);
+            self.mark_initialised(addr..addr + 1);
         }
 
         self.pc += 1;
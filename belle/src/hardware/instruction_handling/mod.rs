@@ -1,3 +1,4 @@
+#![allow(clippy::bool_assert_comparison)] // the tests compare flags with assert_eq!
 pub mod interrupt;
 pub mod jump;
 pub mod load_store;
@@ -536,3 +537,28 @@ fn bg_jump() {
     test_instruction!(bcpu, bg, "$120");
     assert_eq!(bcpu.pc, 120);
 }
+
+#[test]
+fn int_scripted_input() {
+    let mut bcpu = CPU::new();
+    bcpu.input = Some(b"a12\nx\n".iter().copied().collect());
+
+    test_instruction!(bcpu, int, "9");
+    assert_eq!(bcpu.int_reg[0], u16::from(b'a'));
+
+    test_instruction!(bcpu, int, "40");
+    assert_eq!(bcpu.int_reg[0], 12);
+
+    let read = crate::Instruction::INT(crate::Argument::Literal(40));
+    assert!(matches!(
+        bcpu.execute_instruction(&read),
+        Err(crate::UnrecoverableError::ReadFail(..))
+    ));
+    assert!(matches!(
+        bcpu.execute_instruction(&read),
+        Err(crate::UnrecoverableError::ReadFail(..))
+    ));
+
+    test_instruction!(bcpu, int, "9");
+    assert_eq!(bcpu.int_reg[0], interrupt::END_OF_INPUT);
+}
@@ -15,7 +15,9 @@ impl CPU {
                 ));
             }
 
-            self.memory[self.sp as usize] = val as u16;
+            self.write_memory(self.sp as usize, val as u16);
+            self.mark_initialised(self.sp as usize..self.sp as usize + 1);
+            self.note_push();
             self.backward_stack = self.sp >= self.bp;
         } else {
             if self.sp == 0 {
@@ -28,7 +30,9 @@ impl CPU {
                 ));
             }
             self.sp -= 1;
-            self.memory[self.sp as usize] = val as u16;
+            self.write_memory(self.sp as usize, val as u16);
+            self.mark_initialised(self.sp as usize..self.sp as usize + 1);
+            self.note_push();
         }
         self.pc += 1;
         Ok(())
@@ -45,19 +49,22 @@ impl CPU {
                 Some("segmentation fault while executing pop".to_string()),
             ));
         }
+        self.check_initialised(temp as usize)?;
+        self.check_pop()?;
         let v = self.memory[temp as usize];
         if let Register(_) = arg {
             self.set_register_value(arg, v as f64)?;
         } else if let MemAddr(val) = arg {
-            self.memory[*val as usize] = v;
+            self.write_memory(*val as usize, v);
+            self.mark_initialised(*val as usize..*val as usize + 1);
         }
         if self.sp > self.bp {
-            self.memory[self.sp as usize] = 0;
+            self.write_memory(self.sp as usize, 0);
             if self.sp != self.bp {
                 self.sp -= 1;
             }
         } else {
-            self.memory[self.sp as usize] = 0;
+            self.write_memory(self.sp as usize, 0);
             if self.sp != self.bp {
                 self.sp += 1;
             }
@@ -2,18 +2,20 @@ use crate::{Argument::*, *};
 impl CPU {
     pub fn handle_ret(&mut self) -> PossibleCrash {
         let temp: i32 = self.sp as i32;
+        self.check_initialised(temp as usize)?;
         let v = self.memory[temp as usize];
-        if v <= 0 {
+        if v == 0 {
             return Err(UnrecoverableError::StackUnderflow(self.ir, self.pc, None));
         }
+        self.check_ret()?;
         self.pc = v + 1;
         if self.sp > self.bp {
-            self.memory[self.sp as usize] = 0;
+            self.write_memory(self.sp as usize, 0);
             if self.sp != self.bp {
                 self.sp -= 1;
             }
         } else {
-            self.memory[self.sp as usize] = 0;
+            self.write_memory(self.sp as usize, 0);
             if self.sp != self.bp {
                 self.sp += 1;
             }
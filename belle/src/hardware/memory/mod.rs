@@ -1 +1,6 @@
 pub mod rom_loading;
+pub use rom_loading::*;
+pub mod shadow;
+pub use shadow::*;
+pub mod shadow_stack;
+pub use shadow_stack::*;
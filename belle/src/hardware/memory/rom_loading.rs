@@ -1,92 +1,348 @@
-use crate::config::CONFIG;
+use crate::bdb_utils::parse_number;
+use crate::config::{Config, CONFIG};
 use crate::*;
+use basm::{build_rom, find_overlap, rom_checksum, Segment, ROM_HEADER_WORDS};
 use colored::*;
-impl CPU {
-    pub fn load_rom(&mut self, binary: &[i16]) -> Result<(), EmuError> {
-        let mut counter = 0;
-        let mut rom_metadata = String::from("");
-        if let Some(number) = binary.first() {
-            if (number >> 8) == 1 {
-                if number & 0xFF != 2 {
-                    eprintln!(
-                        "{}: {} '{}' version does not match emulator version.",
-                        "warning".yellow(),
-                        "ROM".magenta(),
-                        CONFIG.rom.to_string().green()
-                    );
-                }
-            } else {
-                eprintln!(
-                    "{}: {} '{}' does not have version.\nmay be invalid",
-                    "warning".yellow(),
-                    "ROM".magenta(),
-                    CONFIG.rom.to_string().green()
-                );
-            }
+
+/// The words in front of a ROM's code
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RomHeader {
+    /// The format version, or `None` for a headerless ROM loaded with `--thin`
+    pub version: Option<u8>,
+    pub starts_at: u16,
+    /// The length of the `.data` text in bytes, including padding
+    pub metadata_len: u16,
+    /// The `.data` text, two seven-bit characters per word
+    pub metadata: String,
+    /// The number of segments, from version 3 on
+    pub segment_count: Option<u16>,
+    /// Checksum of everything after the header, from version 3 on
+    pub checksum: Option<u16>,
+    /// Index of the first word after the metadata
+    pub code_offset: usize,
+}
+
+/// The oldest ROM format version that can still be loaded
+pub const OLDEST_ROM_VERSION: u8 = 2;
+
+impl RomHeader {
+    /// Reads the header of a ROM in any supported format version. A thin ROM
+    /// has no header at all and is loaded at the address given with `--thin`.
+    pub fn parse(binary: &[i16], thin: Option<u16>) -> Result<Self, EmuError> {
+        if let Some(starts_at) = thin {
+            return Ok(RomHeader {
+                starts_at,
+                ..RomHeader::default()
+            });
+        }
+        let word = |index: usize| binary.get(index).map(|&val| val as u16);
+        let version = match word(0) {
+            Some(magic) if magic >> 8 == 1 => (magic & 0xFF) as u8,
+            _ => return Err(EmuError::NoRomHeader()),
+        };
+        // the words after the metadata length, which each version gives a different meaning
+        let (header_words, segment_count, checksum) = match version {
+            2 => (3, None, None),
+            3 => (ROM_HEADER_WORDS, word(3), word(4)),
+            _ => return Err(EmuError::RomVersion(version)),
+        };
+        if binary.len() < header_words {
+            return Err(EmuError::RomLength(header_words, binary.len()));
         }
-        if let Some(val) = binary.get(1) {
-            self.starts_at = *val as u16;
+        let metadata_len = word(2).unwrap_or(0);
+        let code_offset = header_words + metadata_len as usize / 2;
+        let mut metadata = String::new();
+        for element in binary.iter().take(code_offset).skip(header_words) {
+            metadata.push(char::from(((element & 0x7F00) >> 8) as u8));
+            metadata.push(char::from((element & 0x7F) as u8));
         }
-        let start_ind = if let Some(val) = binary.get(2) {
-            ((val / 2) + 3) as usize
-        } else {
-            3
+        Ok(RomHeader {
+            version: Some(version),
+            starts_at: word(1).unwrap_or(0),
+            metadata_len,
+            metadata,
+            segment_count,
+            checksum,
+            code_offset,
+        })
+    }
+
+    /// The number of words after the metadata
+    pub fn code_len(&self, binary: &[i16]) -> usize {
+        binary.len().saturating_sub(self.code_offset)
+    }
+
+    /// The blocks of code the ROM loads, cut short where the ROM is truncated.
+    /// Version 2 ROMs have a single segment at the start address.
+    pub fn segments(&self, binary: &[i16]) -> Vec<Segment> {
+        let rest = binary.get(self.code_offset..).unwrap_or_default();
+        let Some(count) = self.segment_count else {
+            return vec![Segment {
+                address: self.starts_at,
+                words: rest.to_vec(),
+            }];
         };
-        for (index, element) in binary.iter().enumerate() {
-            if index < 3 {
-                continue;
-            }
-            if index < start_ind {
-                rom_metadata = format!(
-                    "{}{}",
-                    rom_metadata,
-                    char::from(((element & 0x7F00) >> 8) as u8)
-                );
-                rom_metadata = format!("{}{}", rom_metadata, char::from((element & 0x7F) as u8));
-                continue;
-            } else {
+        let mut segments = Vec::new();
+        let mut index = 0;
+        for _ in 0..count {
+            let (Some(&address), Some(&len)) = (rest.get(index), rest.get(index + 1)) else {
                 break;
-            }
+            };
+            let words = rest.iter().skip(index + 2).take(len as u16 as usize);
+            segments.push(Segment {
+                address: address as u16,
+                words: words.copied().collect(),
+            });
+            index += 2 + len as u16 as usize;
+        }
+        segments
+    }
+
+    // the words the header says follow the metadata
+    fn expected_len(&self, binary: &[i16]) -> Option<usize> {
+        let count = self.segment_count?;
+        let rest = binary.get(self.code_offset..).unwrap_or_default();
+        let mut index = 0;
+        for _ in 0..count {
+            // a segment whose length is cut off is at least its address and length
+            let len = rest.get(index + 1).map_or(0, |&len| len as u16 as usize);
+            index += 2 + len;
         }
-        for (index, element) in binary.iter().enumerate() {
-            if index < start_ind {
-                continue;
+        Some(index)
+    }
+
+    /// Checks the length and checksum the header gives against the rest of the ROM
+    pub fn verify(&self, binary: &[i16]) -> Result<(), EmuError> {
+        if let Some(expected) = self.expected_len(binary) {
+            let found = self.code_len(binary);
+            if found != expected {
+                return Err(EmuError::RomLength(expected, found));
             }
-            if counter + self.starts_at as usize >= MEMORY_SIZE {
-                return Err(EmuError::MemoryOverflow());
+        }
+        if let Some(expected) = self.checksum {
+            let found = self.body_checksum(binary);
+            if found != expected {
+                return Err(EmuError::RomChecksum(expected, found));
             }
-            self.memory[counter + self.starts_at as usize] = *element as u16;
+        }
+        Ok(())
+    }
+
+    /// The checksum of everything after the header, as basm computes it
+    pub fn body_checksum(&self, binary: &[i16]) -> u16 {
+        let body: Vec<u16> = binary
+            .iter()
+            .skip(ROM_HEADER_WORDS)
+            .map(|&word| word as u16)
+            .collect();
+        rom_checksum(&body)
+    }
+}
+
+/// A raw image given with `--load FILE@ADDR`, copied over memory after the ROM
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlay {
+    pub path: String,
+    pub address: u16,
+}
 
-            counter += 1;
+impl Overlay {
+    /// Reads the image as the segment it loads
+    pub fn read(&self) -> Result<Segment, EmuError> {
+        let words = create_rom(&self.path)
+            .map_err(|e| EmuError::OverlayRead(self.path.clone(), e.to_string()))?;
+        Ok(Segment {
+            address: self.address,
+            words,
+        })
+    }
+}
+
+/// How a ROM is put into memory. The emulator takes these from the command
+/// line, and the test runner from each test case.
+#[derive(Debug, Default, Clone)]
+pub struct LoadOptions {
+    /// Load a headerless ROM at this address
+    pub thin: Option<u16>,
+    pub overlays: Vec<Overlay>,
+    pub uninit: Option<CheckMode>,
+    pub call_stack: Option<CheckMode>,
+    pub strict: bool,
+    /// Arguments placed at the top of memory
+    pub args: Vec<String>,
+}
+
+impl LoadOptions {
+    pub fn from_config(config: &Config) -> Self {
+        LoadOptions {
+            thin: config.thin,
+            overlays: config.load.clone(),
+            uninit: config.uninit,
+            call_stack: config.call_stack,
+            strict: config.strict,
+            args: config.args.clone(),
         }
+    }
+}
+
+/// Parses the address given to `--thin`
+pub fn parse_address(text: &str) -> Result<u16, String> {
+    parse_number::<u16>(text).map_err(|e| format!("invalid address '{text}': {e}"))
+}
+
+/// Rewrites a ROM of an older format version, or a thin one, in the current format
+pub fn upgrade_rom(binary: &[i16], thin: Option<u16>) -> Result<Vec<u8>, EmuError> {
+    let header = RomHeader::parse(binary, thin)?;
+    header.verify(binary)?;
+    let metadata = header.metadata.trim_end_matches('\0');
+    Ok(build_rom(
+        header.starts_at,
+        metadata,
+        &header.segments(binary),
+    ))
+}
+
+/// Parses a `FILE@ADDR` overlay. The file is read when the ROM is loaded.
+pub fn parse_overlay(text: &str) -> Result<Overlay, String> {
+    let (path, address) = text
+        .rsplit_once('@')
+        .ok_or_else(|| "expected FILE@ADDRESS".to_string())?;
+    Ok(Overlay {
+        path: path.to_string(),
+        address: parse_address(address)?,
+    })
+}
+
+impl CPU {
+    /// Loads a ROM with the options given on the command line, and starts
+    /// scripted input over
+    pub fn load_rom(&mut self, binary: &[i16]) -> Result<(), EmuError> {
+        let metadata = self.load_rom_with(binary, &LoadOptions::from_config(&CONFIG))?;
+        // reloading a program starts its input over
+        self.input = scripted_input();
         if CONFIG.verbose {
-            if !rom_metadata.is_empty() {
-                let longest_length = rom_metadata
-                    .lines()
-                    .map(|line| line.len())
-                    .max()
-                    .unwrap_or(0);
-                let longest_length = if longest_length > 12 {
-                    longest_length
-                } else {
-                    12
-                };
-                let val = if longest_length % 2 == 0 { 5 } else { 4 };
-                println!(
-                    "╔{}╡ {} ╞{}╗",
-                    "═".repeat((longest_length / 2) - 5),
-                    "METADATA".bright_green(),
-                    "═".repeat((longest_length / 2) - val)
-                );
-                for line in rom_metadata.lines() {
-                    println!("║ {:width$} ║", line, width = longest_length);
-                }
-                println!("╚{}╝", "═".repeat(longest_length + 2));
-            } else {
-                println!("=====NO METADATA====");
-            }
+            print_metadata(&metadata);
+        }
+        Ok(())
+    }
+
+    /// Loads a ROM and its overlays, places the arguments and points pc at the
+    /// start address. Returns the ROM's `.data` text.
+    pub fn load_rom_with(
+        &mut self,
+        binary: &[i16],
+        options: &LoadOptions,
+    ) -> Result<String, EmuError> {
+        let header = RomHeader::parse(binary, options.thin)?;
+        header.verify(binary)?;
+        self.starts_at = header.starts_at;
+        let mut segments = header.segments(binary);
+        for overlay in &options.overlays {
+            segments.push(overlay.read()?);
+        }
+        if let Some(check) = options.uninit {
+            self.shadow = Some(ShadowMemory::new(check));
+        }
+        if let Some(check) = options.call_stack {
+            self.call_stack = Some(ShadowStack::new(check));
+        }
+        self.load_segments(&segments)?;
+        if options.strict {
+            self.strict = true;
+        }
+        if !options.args.is_empty() {
+            self.set_args(&options.args, &segments)?;
         }
         self.pc = self.starts_at;
+        Ok(header.metadata)
+    }
+
+    /// Copies segments into memory, failing before anything is copied if one
+    /// runs past the end of memory or two of them fill the same address
+    pub fn load_segments(&mut self, segments: &[Segment]) -> Result<(), EmuError> {
+        if segments.iter().any(|segment| segment.end() > MEMORY_SIZE) {
+            return Err(EmuError::MemoryOverflow());
+        }
+        if let Some((first, second)) = find_overlap(segments) {
+            return Err(EmuError::SegmentOverlap(
+                (first.address, (first.end() - 1) as u16),
+                (second.address, (second.end() - 1) as u16),
+            ));
+        }
+        for segment in segments {
+            for (offset, &word) in segment.words.iter().enumerate() {
+                self.memory[segment.address as usize + offset] = word as u16;
+            }
+            self.mark_initialised(segment.address as usize..segment.end());
+        }
         Ok(())
     }
+
+    /// Copies program arguments to the top of memory: a table of one pointer
+    /// per argument, followed by the arguments themselves, one character per
+    /// word and zero-terminated like `.asciiz`. r0 is set to the number of
+    /// arguments and r1 to the address of the table. Fails if the arguments
+    /// would overwrite one of the loaded `segments`.
+    pub fn set_args(&mut self, args: &[String], segments: &[Segment]) -> Result<(), EmuError> {
+        let strings: Vec<Vec<u16>> = args
+            .iter()
+            .map(|arg| arg.chars().map(|c| c as u32 as u16).chain([0]).collect())
+            .collect();
+        let size = args.len() + strings.iter().map(Vec::len).sum::<usize>();
+        let table = MEMORY_SIZE
+            .checked_sub(size)
+            .ok_or_else(EmuError::MemoryOverflow)?;
+        // the arguments may not overwrite the program, even where it holds zeroes
+        if let Some(segment) = segments
+            .iter()
+            .find(|segment| !segment.words.is_empty() && segment.end() > table)
+        {
+            return Err(EmuError::ArgsOverlap(
+                table as u16,
+                (segment.address, (segment.end() - 1) as u16),
+            ));
+        }
+        // nor anything else already placed there, like earlier arguments
+        if self.memory[table..].iter().any(|&word| word != 0) {
+            return Err(EmuError::MemoryOverflow());
+        }
+        let mut next = table + args.len();
+        for (index, string) in strings.iter().enumerate() {
+            self.memory[table + index] = next as u16;
+            self.memory[next..next + string.len()].copy_from_slice(string);
+            next += string.len();
+        }
+        self.mark_initialised(table..MEMORY_SIZE);
+        self.int_reg[0] = args.len() as u16;
+        self.int_reg[1] = table as u16;
+        Ok(())
+    }
+}
+
+fn print_metadata(rom_metadata: &str) {
+    if rom_metadata.is_empty() {
+        println!("=====NO METADATA====");
+        return;
+    }
+    let longest_length = rom_metadata
+        .lines()
+        .map(|line| line.len())
+        .max()
+        .unwrap_or(0);
+    let longest_length = if longest_length > 12 {
+        longest_length
+    } else {
+        12
+    };
+    let val = if longest_length % 2 == 0 { 5 } else { 4 };
+    println!(
+        "╔{}╡ {} ╞{}╗",
+        "═".repeat((longest_length / 2) - 5),
+        "METADATA".bright_green(),
+        "═".repeat((longest_length / 2) - val)
+    );
+    for line in rom_metadata.lines() {
+        println!("║ {:width$} ║", line, width = longest_length);
+    }
+    println!("╚{}╝", "═".repeat(longest_length + 2));
 }
@@ -0,0 +1,87 @@
+use crate::*;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// What a checker does when the program breaks its rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckMode {
+    /// Print a warning and carry on
+    Warn,
+    /// Crash at the offending instruction
+    Fault,
+}
+
+/// One bit per word of memory, set once the word holds something the ROM or
+/// the program put there
+#[derive(Debug, Clone)]
+pub struct ShadowMemory {
+    pub check: CheckMode,
+    bits: Vec<u64>,
+}
+
+impl ShadowMemory {
+    pub fn new(check: CheckMode) -> Self {
+        ShadowMemory {
+            check,
+            bits: vec![0; MEMORY_SIZE / 64],
+        }
+    }
+
+    pub fn mark(&mut self, addr: usize) {
+        if let Some(bits) = self.bits.get_mut(addr / 64) {
+            *bits |= 1 << (addr % 64);
+        }
+    }
+
+    pub fn mark_range(&mut self, range: Range<usize>) {
+        for addr in range {
+            self.mark(addr);
+        }
+    }
+
+    pub fn is_initialised(&self, addr: usize) -> bool {
+        self.bits
+            .get(addr / 64)
+            .is_some_and(|bits| bits & (1 << (addr % 64)) != 0)
+    }
+}
+
+impl CPU {
+    /// Counts a word as initialised, if uninitialised reads are being checked
+    pub fn mark_initialised(&mut self, range: Range<usize>) {
+        if let Some(shadow) = self.shadow.as_mut() {
+            shadow.mark_range(range);
+        }
+    }
+
+    /// Reports a read of a word that was never written, as a warning or a
+    /// crash depending on the check
+    pub fn check_initialised(&mut self, addr: usize) -> PossibleCrash {
+        let Some(shadow) = self.shadow.as_mut() else {
+            return Ok(());
+        };
+        if shadow.is_initialised(addr) {
+            return Ok(());
+        }
+        let message = format!("read of uninitialised memory at x{addr:04X}");
+        match shadow.check {
+            CheckMode::Warn => {
+                // one warning per address is enough
+                shadow.mark(addr);
+                self.warn(RecoverableError::UninitialisedRead(self.pc, Some(message)));
+                Ok(())
+            }
+            CheckMode::Fault => {
+                self.running = false;
+                self.err = true;
+                Err(UnrecoverableError::UninitialisedRead(
+                    self.ir,
+                    self.pc,
+                    Some(message),
+                ))
+            }
+        }
+    }
+}
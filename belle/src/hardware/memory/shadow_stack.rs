@@ -0,0 +1,130 @@
+use crate::*;
+
+/// A return address on the stack, pushed by a taken jump while pushret is set
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CallFrame {
+    /// The stack slot holding the return address
+    pub slot: u16,
+    /// The jump that pushed it
+    pub site: u16,
+    /// Where the jump went
+    pub routine: u16,
+}
+
+/// The return addresses on the stack, outermost first, kept beside the real
+/// stack so that `RET` and `POP` can tell them apart from data
+#[derive(Debug, Clone)]
+pub struct ShadowStack {
+    pub check: CheckMode,
+    pub frames: Vec<CallFrame>,
+}
+
+impl ShadowStack {
+    pub fn new(check: CheckMode) -> Self {
+        ShadowStack {
+            check,
+            frames: Vec::new(),
+        }
+    }
+
+    fn frame_at(&self, slot: u16) -> Option<usize> {
+        self.frames.iter().rposition(|frame| frame.slot == slot)
+    }
+}
+
+impl CPU {
+    // whether a stack slot is between the stack pointer and the base pointer
+    fn slot_is_live(&self, slot: u16) -> bool {
+        if self.sp > self.bp || self.backward_stack {
+            slot > self.bp && slot <= self.sp
+        } else {
+            slot >= self.sp && slot < self.bp
+        }
+    }
+
+    /// Records the return address a jump just pushed, once it has jumped
+    pub fn note_call(&mut self, site: u16) {
+        let (slot, routine) = (self.sp, self.pc);
+        if let Some(stack) = self.call_stack.as_mut() {
+            stack.frames.push(CallFrame {
+                slot,
+                site,
+                routine,
+            });
+        }
+    }
+
+    /// Forgets a return address that a `PUSH` has written over
+    pub fn note_push(&mut self) {
+        let slot = self.sp;
+        if let Some(stack) = self.call_stack.as_mut() {
+            stack.frames.retain(|frame| frame.slot != slot);
+        }
+    }
+
+    /// Checks that a `POP` is about to take data off the stack, not a return
+    /// address. The frame is kept, so that the routine's `RET` is caught too.
+    pub fn check_pop(&mut self) -> PossibleCrash {
+        let Some(stack) = self.call_stack.as_ref() else {
+            return Ok(());
+        };
+        let Some(frame) = stack.frame_at(self.sp).map(|index| stack.frames[index]) else {
+            return Ok(());
+        };
+        self.call_stack_error(format!(
+            "POP took the return address x{:04X} pushed by the jump at x{:04X}",
+            self.memory[frame.slot as usize], frame.site
+        ))
+    }
+
+    /// Checks that a `RET` is about to pop the return address of the routine it
+    /// returns from, with the stack as deep as when the routine was entered
+    pub fn check_ret(&mut self) -> PossibleCrash {
+        let Some(mut stack) = self.call_stack.take() else {
+            return Ok(());
+        };
+        let value = self.memory[self.sp as usize];
+        let problem = match (stack.frame_at(self.sp), stack.frames.last()) {
+            (Some(index), _) if index + 1 == stack.frames.len() => None,
+            (Some(_), Some(frame)) => Some(format!(
+                "the routine at x{:04X} entered with the stack at x{:04X} but returned with it at x{:04X}",
+                frame.routine, frame.slot, self.sp
+            )),
+            (None, Some(frame)) if self.slot_is_live(frame.slot) => Some(format!(
+                "RET popped the data value x{value:04X} instead of a return address; the routine at x{:04X} entered with the stack at x{:04X} but returned with it at x{:04X}",
+                frame.routine, frame.slot, self.sp
+            )),
+            _ => Some(format!(
+                "RET popped the data value x{value:04X} instead of a return address"
+            )),
+        };
+        // the slot being popped, and any return addresses left above it, are gone
+        let sp = self.sp;
+        stack
+            .frames
+            .retain(|frame| frame.slot != sp && self.slot_is_live(frame.slot));
+        self.call_stack = Some(stack);
+        match problem {
+            Some(message) => self.call_stack_error(message),
+            None => Ok(()),
+        }
+    }
+
+    fn call_stack_error(&mut self, message: String) -> PossibleCrash {
+        match self.call_stack.as_ref().map(|stack| stack.check) {
+            Some(CheckMode::Fault) => {
+                self.running = false;
+                self.err = true;
+                Err(UnrecoverableError::CallStackMismatch(
+                    self.ir,
+                    self.pc,
+                    Some(message),
+                ))
+            }
+            _ => {
+                self.warn(RecoverableError::CallStackMismatch(self.pc, Some(message)));
+                Ok(())
+            }
+        }
+    }
+}
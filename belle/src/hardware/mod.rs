@@ -1,3 +1,5 @@
+pub mod call;
+pub use call::*;
 pub mod core;
 pub use core::*;
 pub mod error_generation;
@@ -20,3 +22,374 @@ fn set_register_0() {
     set_register!(bcpu, 0, -4.9);
     assert_eq!(bcpu.int_reg[0], 3);
 }
+
+#[test]
+fn call_subroutine() {
+    let mut bcpu = CPU::new();
+    bcpu.pc = 0x100;
+    for (offset, source) in ["add r0, r1", "int 71", "jmp [517]", "hlt", "hlt", "ret"]
+        .iter()
+        .enumerate()
+    {
+        let words = crate::assemble(source).unwrap();
+        bcpu.memory[0x200 + offset] = words[0];
+    }
+    let symbols = crate::SymbolTable::parse("label sum 0x0200\n");
+    bcpu.int_reg[0] = 2;
+    bcpu.int_reg[1] = 3;
+
+    let cycles = bcpu.call_label(&symbols, "sum", 100).unwrap();
+    assert_eq!(cycles, 4);
+    assert_eq!(bcpu.int_reg[0], 5);
+    assert_eq!(bcpu.pc, 0x101);
+    assert_eq!(bcpu.sp, bcpu.bp);
+
+    assert!(matches!(
+        bcpu.call(0x203, 100),
+        Err(crate::CallError::Halted(_))
+    ));
+    assert!(matches!(
+        bcpu.call_label(&symbols, "missing", 100),
+        Err(crate::CallError::UnknownLabel(_))
+    ));
+
+    // return addresses from the top half of memory survive the push
+    bcpu.pc = 0x9000;
+    assert_eq!(bcpu.call(0x205, 100).unwrap(), 1);
+    assert_eq!(bcpu.pc, 0x9001);
+
+    let path = std::env::temp_dir().join(format!("belle-call-{}.bin", std::process::id()));
+    let segment = basm::Segment {
+        address: 0x100,
+        words: vec![crate::assemble("ret").unwrap()[0] as i16],
+    };
+    std::fs::write(&path, basm::build_rom(0x100, "", &[segment])).unwrap();
+    let loaded = CPU::from_rom(&path.to_string_lossy());
+    let _ = std::fs::remove_file(&path);
+    let mut loaded = loaded.unwrap();
+    assert_eq!(loaded.pc, 0x100);
+    assert!(loaded.input.is_none());
+    assert_eq!(loaded.call(0x100, 10).unwrap(), 1);
+}
+
+#[test]
+fn program_arguments() {
+    let mut bcpu = CPU::new();
+    bcpu.set_args(&["ab".to_string(), "c".to_string()], &[])
+        .unwrap();
+    assert_eq!(bcpu.int_reg[0], 2);
+    let table = bcpu.int_reg[1] as usize;
+    assert_eq!(table, MEMORY_SIZE - 7);
+    let first = bcpu.memory[table] as usize;
+    let second = bcpu.memory[table + 1] as usize;
+    assert_eq!(bcpu.memory[first..first + 3], [b'a' as u16, b'b' as u16, 0]);
+    assert_eq!(bcpu.memory[second..second + 2], [b'c' as u16, 0]);
+
+    // a second set would overwrite the first
+    assert!(bcpu.set_args(&["x".to_string()], &[]).is_err());
+
+    // a segment of zeroes at the top of memory is still part of the program
+    let segment = basm::Segment {
+        address: 0xFFF0,
+        words: vec![0; 16],
+    };
+    assert!(matches!(
+        CPU::new().set_args(&["x".to_string()], &[segment]),
+        Err(crate::EmuError::ArgsOverlap(0xFFFD, (0xFFF0, 0xFFFF)))
+    ));
+}
+
+#[test]
+fn rom_length_and_checksum() {
+    use basm::Segment;
+    let words = |bytes: Vec<u8>| -> Vec<i16> {
+        bytes
+            .chunks(2)
+            .map(|pair| i16::from_be_bytes([pair[0], pair[1]]))
+            .collect()
+    };
+    let segments = [
+        Segment {
+            address: 0x100,
+            words: vec![0x1102, 0x0000],
+        },
+        Segment {
+            address: 0x400,
+            words: vec![7],
+        },
+    ];
+    let rom = words(basm::build_rom(0x100, "hi!", &segments));
+    let header = RomHeader::parse(&rom, None).unwrap();
+    assert_eq!(header.metadata.trim_end_matches('\0'), "hi!");
+    assert_eq!(header.segments(&rom), segments);
+    let mut bcpu = CPU::new();
+    bcpu.load_rom(&rom).unwrap();
+    assert_eq!(bcpu.memory[0x101], 0);
+    assert_eq!(bcpu.memory[0x400], 7);
+
+    let mut corrupted = rom.clone();
+    corrupted[basm::ROM_HEADER_WORDS] ^= 1;
+    assert!(matches!(
+        CPU::new().load_rom(&corrupted),
+        Err(crate::EmuError::RomChecksum(..))
+    ));
+    assert!(matches!(
+        CPU::new().load_rom(&rom[..rom.len() - 1]),
+        Err(crate::EmuError::RomLength(7, 6))
+    ));
+
+    let overlay = Segment {
+        address: 0x101,
+        words: vec![1, 2],
+    };
+    assert!(matches!(
+        CPU::new().load_segments(&[segments[0].clone(), overlay]),
+        Err(crate::EmuError::SegmentOverlap(
+            (0x100, 0x101),
+            (0x101, 0x102)
+        ))
+    ));
+}
+
+#[test]
+fn org_segments() {
+    use std::collections::BTreeMap;
+    let labels = BTreeMap::new();
+    let segments =
+        basm::assemble_segments("add r0, 1\n.org [0x400]\nhlt\nhlt", 0x100, &labels).unwrap();
+    let placed: Vec<(u16, usize)> = segments
+        .iter()
+        .map(|segment| (segment.address, segment.words.len()))
+        .collect();
+    assert_eq!(placed, [(0x100, 1), (0x400, 2)]);
+
+    let rom: Vec<i16> = basm::build_rom(0x100, "", &segments)
+        .chunks(2)
+        .map(|pair| i16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    let mut bcpu = CPU::new();
+    bcpu.load_rom_with(&rom, &LoadOptions::default()).unwrap();
+    assert_eq!(bcpu.memory[0x100], 0x1101);
+    assert_eq!(bcpu.memory[0x101], 0);
+    assert_eq!(bcpu.pc, 0x100);
+
+    // addresses outside memory, and segments that run off its end
+    assert!(basm::assemble_segments("hlt\n.org [70000]\nhlt", 0x100, &labels).is_err());
+    assert!(basm::assemble_segments(".org [0xFFFF]\nhlt\nhlt", 0x100, &labels).is_err());
+    assert!(basm::assemble_segments(".org [0xFFFE]\nhlt\nhlt", 0x100, &labels).is_ok());
+}
+
+#[test]
+fn load_overlay() {
+    use crate::EmuError;
+    let path = std::env::temp_dir().join(format!("belle-overlay-{}.bin", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    // parsing the flag does not read the file
+    let overlay = parse_overlay(&format!("{path}@0x400")).unwrap();
+    assert_eq!(overlay.address, 0x400);
+    let options = LoadOptions {
+        overlays: vec![overlay],
+        ..LoadOptions::default()
+    };
+    let rom: Vec<i16> = basm::build_rom(
+        0x100,
+        "",
+        &[basm::Segment {
+            address: 0x100,
+            words: vec![0],
+        }],
+    )
+    .chunks(2)
+    .map(|pair| i16::from_be_bytes([pair[0], pair[1]]))
+    .collect();
+    assert!(matches!(
+        CPU::new().load_rom_with(&rom, &options),
+        Err(EmuError::OverlayRead(..))
+    ));
+
+    std::fs::write(&path, [0x12, 0x34, 0x00, 0x07]).unwrap();
+    let mut bcpu = CPU::new();
+    let loaded = bcpu.load_rom_with(&rom, &options);
+    let clashing = LoadOptions {
+        overlays: vec![parse_overlay(&format!("{path}@0x0FF")).unwrap()],
+        ..LoadOptions::default()
+    };
+    let overlap = CPU::new().load_rom_with(&rom, &clashing);
+    let _ = std::fs::remove_file(&path);
+
+    loaded.unwrap();
+    assert_eq!(bcpu.memory[0x400..0x402], [0x1234, 7]);
+    assert!(matches!(overlap, Err(EmuError::SegmentOverlap(..))));
+    assert!(parse_overlay("no-address").is_err());
+}
+
+#[test]
+fn rom_versions() {
+    use crate::EmuError;
+    // version 2: magic, start, metadata length, one word of metadata, code
+    let old = [0x0102, 0x100, 2, 0x6869, 0x1102];
+    let mut bcpu = CPU::new();
+    bcpu.load_rom(&old).unwrap();
+    assert_eq!(bcpu.memory[0x100], 0x1102);
+
+    let upgraded: Vec<i16> = upgrade_rom(&old, None)
+        .unwrap()
+        .chunks(2)
+        .map(|pair| i16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    let header = RomHeader::parse(&upgraded, None).unwrap();
+    assert_eq!(header.version, Some(basm::ROM_VERSION));
+    assert_eq!(header.metadata, "hi");
+    assert_eq!(header.segments(&upgraded)[0].words, [0x1102]);
+
+    let thin = RomHeader::parse(&[0x1102], Some(0x200)).unwrap();
+    assert_eq!(thin.segments(&[0x1102])[0].address, 0x200);
+    assert!(matches!(
+        RomHeader::parse(&[0x1102], None),
+        Err(EmuError::NoRomHeader())
+    ));
+    assert!(matches!(
+        RomHeader::parse(&[0x0104, 0x100, 0, 0, 0], None),
+        Err(EmuError::RomVersion(4))
+    ));
+    assert!(matches!(
+        RomHeader::parse(&[0x0109, 0x100, 0], None),
+        Err(EmuError::RomVersion(9))
+    ));
+}
+
+#[test]
+fn strict_decoding() {
+    let source =
+        "hlt\nadd r1, -3\nmov r2, r9\nmov r3, &r4\nmov r0, &$20\npush 5\npush r1\npop r2\n\
+                  pop [300]\nint 0\nst [40], r3\nst &r2, r1\nld r1, [300]\nlea r2, [12]\n\
+                  jmp [517]\njmp &r3\nbnz [12]\nbg &r1\nret\nbl [3]\ncmp r0, 1\nnand r1, r2";
+    for word in crate::assemble(source).unwrap() {
+        assert_eq!(
+            CPU::check_encoding(word),
+            Ok(()),
+            "{}",
+            CPU::decode_word(word)
+        );
+    }
+    for bad in [0x0001, 0xE00C, 0xE070, 0x8800, 0xD000, 0x3010, 0x7C08] {
+        assert!(CPU::check_encoding(bad).is_err(), "{bad:04X}");
+    }
+
+    let mut bcpu = CPU::new();
+    bcpu.strict = true;
+    bcpu.ir = 0xE00C_u16 as i16;
+    let parsed_ins = bcpu.decode_instruction();
+    assert!(matches!(
+        bcpu.execute_instruction(&parsed_ins),
+        Err(UnrecoverableError::IllegalInstruction(..))
+    ));
+}
+
+#[test]
+fn uninitialised_reads() {
+    let source = "st [120], r1\nld r0, [120]\nld r2, [256]\nld r3, [121]\nhlt";
+    let words: Vec<i16> = crate::assemble(source)
+        .unwrap()
+        .into_iter()
+        .map(|word| word as i16)
+        .collect();
+    let run = |check: CheckMode| -> Result<CPU, UnrecoverableError> {
+        let mut bcpu = CPU::new();
+        bcpu.shadow = Some(ShadowMemory::new(check));
+        bcpu.load_segments(&[basm::Segment {
+            address: 0x100,
+            words: words.clone(),
+        }])
+        .unwrap();
+        bcpu.pc = 0x100;
+        bcpu.running = true;
+        while bcpu.running {
+            bcpu.ir = bcpu.memory[bcpu.pc as usize] as i16;
+            let parsed_ins = bcpu.decode_instruction();
+            bcpu.execute_instruction(&parsed_ins)?;
+        }
+        Ok(bcpu)
+    };
+
+    // the word the program stored and the ROM itself are fine, 121 is not
+    let Err(e) = run(CheckMode::Fault) else {
+        panic!("read of x0079 was not caught");
+    };
+    assert_eq!(e.kind(), "Uninitialised read");
+    assert_eq!(e.location(), 0x103);
+    assert!(e.message().unwrap().contains("x0079"));
+
+    let bcpu = run(CheckMode::Warn).unwrap();
+    assert_eq!(bcpu.int_reg[2], words[0] as u16);
+    assert!(bcpu.shadow.unwrap().is_initialised(121));
+}
+
+#[test]
+fn call_stack_checks() {
+    let call = |routine: &str| {
+        let mut bcpu = CPU::new();
+        bcpu.call_stack = Some(ShadowStack::new(CheckMode::Fault));
+        bcpu.pc = 0x100;
+        for (offset, word) in crate::assemble(routine).unwrap().into_iter().enumerate() {
+            bcpu.memory[0x200 + offset] = word;
+        }
+        match bcpu.call(0x200, 100) {
+            Err(crate::CallError::Crashed(e)) => Err(e.message().unwrap_or_default().to_string()),
+            other => Ok(other.is_ok()),
+        }
+    };
+
+    assert_eq!(call("push 5\npop r1\nret"), Ok(true));
+    assert!(call("push 5\nret")
+        .unwrap_err()
+        .contains("data value x0005"));
+    assert!(call("pop r1\nret").unwrap_err().starts_with("POP took"));
+
+    // a debugger moving the stack pointer back over the inner return address
+    let mut bcpu = CPU::new();
+    bcpu.call_stack = Some(ShadowStack::new(CheckMode::Fault));
+    for (addr, source) in [(0x100, "jmp [512]"), (0x200, "jmp [528]"), (0x210, "ret")] {
+        bcpu.memory[addr] = crate::assemble(source).unwrap()[0];
+    }
+    bcpu.pc = 0x100;
+    for _ in 0..2 {
+        bcpu.ir = bcpu.memory[bcpu.pc as usize] as i16;
+        let parsed_ins = bcpu.decode_instruction();
+        bcpu.execute_instruction(&parsed_ins).unwrap();
+    }
+    bcpu.sp += 1;
+    bcpu.ir = bcpu.memory[bcpu.pc as usize] as i16;
+    let parsed_ins = bcpu.decode_instruction();
+    let e = bcpu.execute_instruction(&parsed_ins).unwrap_err();
+    assert_eq!(e.kind(), "Call stack mismatch");
+    assert!(e.message().unwrap().contains("routine at x0210"));
+}
+
+#[test]
+fn plain_errors_and_warnings() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let crash = UnrecoverableError::DivideByZero(0x1234, 0x102, Some("by r1".to_string()));
+    assert_eq!(crash.to_string(), "Divide by zero at x0102: by r1");
+    assert_eq!(crash.ir(), 0x1234);
+    assert!(!crate::EmuError::RomChecksum(1, 2)
+        .to_string()
+        .contains('\x1b'));
+
+    static OVERFLOWS: AtomicUsize = AtomicUsize::new(0);
+    fn count(warning: &RecoverableError) {
+        if let RecoverableError::Overflow(..) = warning {
+            OVERFLOWS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+    // the handler belongs to the CPU, so no other test's warnings reach it
+    let mut bcpu = CPU::new();
+    bcpu.warning_handler = Some(count);
+    set_register!(bcpu, 0, 70000.0);
+    assert!(bcpu.oflag);
+    assert_eq!(OVERFLOWS.load(Ordering::SeqCst), 1);
+    let mut quiet = CPU::new();
+    set_register!(quiet, 0, 70000.0);
+    assert!(quiet.oflag);
+    assert_eq!(OVERFLOWS.load(Ordering::SeqCst), 1);
+}
@@ -8,7 +8,7 @@ impl fmt::Display for CPU {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if CONFIG.compact_print {
             if self.err {
-                writeln!(f, "{}", self.errmsg)?;
+                writeln!(f, "{}", crash_summary(&self.errmsg))?;
                 return Ok(());
             }
             let r0 = self.int_reg[0].to_string().magenta();
@@ -61,8 +61,9 @@ impl fmt::Display for CPU {
             write!(f, " │ ")?;
         }
         if self.err {
-            write!(f, "{}", self.errmsg)?;
-            let length = length_without_ansi(&exit) + length_without_ansi(self.errmsg.trim());
+            let errmsg = crash_summary(&self.errmsg);
+            write!(f, "{errmsg}")?;
+            let length = length_without_ansi(&exit) + length_without_ansi(errmsg.trim());
             for _ in length..61 {
                 write!(f, " ")?;
             }
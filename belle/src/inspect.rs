@@ -0,0 +1,200 @@
+use crate::config::TraceArgs;
+use crate::*;
+use colored::Colorize;
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+};
+
+// the header of a ROM, or nothing after printing why it cannot be read
+fn read_header(binary: &[i16], thin: Option<u16>) -> Option<RomHeader> {
+    RomHeader::parse(binary, thin)
+        .inspect_err(|e| eprintln!("{}", render_emu_error(e)))
+        .ok()
+}
+
+/// Prints every field of a ROM's header, and whether the ROM matches it.
+/// Returns whether the header could be read.
+pub fn print_info(path: &str, thin: Option<u16>) -> io::Result<bool> {
+    let binary = create_rom(path)?;
+    let Some(header) = read_header(&binary, thin) else {
+        return Ok(false);
+    };
+    let words = header.code_len(&binary);
+    let field = |name: &str| format!("{name}:").bold();
+    println!("{:16} {}", field("ROM"), path.green());
+    match header.version {
+        Some(version) => println!("{:16} {version}", field("Version")),
+        None => println!("{:16} {}", field("Version"), "none (thin)".yellow()),
+    }
+    println!(
+        "{:16} {}",
+        field("Start"),
+        format!("x{:04X}", header.starts_at).magenta()
+    );
+    println!(
+        "{:16} {} bytes",
+        field("Metadata length"),
+        header.metadata_len
+    );
+    match header.segment_count {
+        Some(count) => println!("{:16} {count}", field("Segments")),
+        None => println!("{:16} {words} words", field("Code length")),
+    }
+    if let Some(expected) = header.checksum {
+        let found = header.body_checksum(&binary);
+        let status = if found == expected {
+            "(ok)".green()
+        } else {
+            format!("(ROM has {found:04X})").red()
+        };
+        println!("{:16} {expected:04X} {status}", field("Checksum"));
+    }
+    for segment in header.segments(&binary) {
+        println!(
+            "{:16} x{:04X} to x{:04X} ({} words)",
+            field("Loads at"),
+            segment.address,
+            segment.end().saturating_sub(1),
+            segment.words.len()
+        );
+    }
+    println!("{:16} {} bytes", field("Size"), binary.len() * 2);
+    let metadata = header.metadata.trim_end_matches('\0');
+    if metadata.is_empty() {
+        println!("{:16} {}", field("Metadata"), "none".yellow());
+    } else {
+        println!("{}", field("Metadata"));
+        for line in metadata.lines() {
+            println!("    {line}");
+        }
+    }
+    Ok(true)
+}
+
+/// Prints every word of a ROM's code as the instruction it decodes to.
+/// Returns whether the header could be read.
+pub fn print_disasm(path: &str, thin: Option<u16>) -> io::Result<bool> {
+    let binary = create_rom(path)?;
+    let Some(header) = read_header(&binary, thin) else {
+        return Ok(false);
+    };
+    let symbols = SymbolTable::for_rom(path);
+    for segment in header.segments(&binary) {
+        for (offset, &word) in segment.words.iter().enumerate() {
+            let addr = segment.address.wrapping_add(offset as u16);
+            if let Some(label) = symbols.name_at(addr) {
+                println!("{}", format!("{label}:").blue());
+            }
+            println!(
+                "{}: {:04X}  {}",
+                format!("x{addr:04X}").magenta(),
+                word as u16,
+                CPU::decode_word(word as u16).to_string().green()
+            );
+        }
+    }
+    Ok(true)
+}
+
+/// Rewrites a ROM in the current format, over the original unless given an
+/// output path. Returns whether it could be upgraded.
+pub fn upgrade_rom_file(path: &str, output: Option<&str>, thin: Option<u16>) -> io::Result<bool> {
+    let binary = create_rom(path)?;
+    let Some(header) = read_header(&binary, thin) else {
+        return Ok(false);
+    };
+    if header.version == Some(basm::ROM_VERSION) {
+        println!(
+            "{} is already format version {}",
+            path.green(),
+            basm::ROM_VERSION
+        );
+        return Ok(true);
+    }
+    let bytes = match upgrade_rom(&binary, thin) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("{}", render_emu_error(&e));
+            return Ok(false);
+        }
+    };
+    let output = output.unwrap_or(path);
+    fs::write(output, bytes)?;
+    let from = header.version.map_or("a thin ROM".to_string(), |version| {
+        format!("version {version}")
+    });
+    println!(
+        "Upgraded {} from {from} to version {}",
+        output.green(),
+        basm::ROM_VERSION
+    );
+    Ok(true)
+}
+
+/// Runs a ROM, writing each instruction to the trace before it executes.
+/// Returns whether the program ran without crashing.
+pub fn run_trace(args: &TraceArgs) -> io::Result<bool> {
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+    let symbols = SymbolTable::for_rom(&args.rom);
+    let mut cpu = CPU::new();
+    cpu.warning_handler = Some(print_warning);
+    if let Err(e) = cpu.load_rom(&create_rom(&args.rom)?) {
+        eprintln!("{}", render_emu_error(&e));
+        return Ok(false);
+    }
+    cpu.running = true;
+    let mut cycles = 0;
+    while cpu.running && args.limit.is_none_or(|limit| cycles < limit) {
+        cycles += 1;
+        cpu.ir = cpu.memory[cpu.pc as usize] as i16;
+        let parsed_ins = cpu.decode_instruction();
+        writeln!(out, "{} {parsed_ins}", symbols.describe(cpu.pc))?;
+        if let Err(e) = cpu.execute_instruction(&parsed_ins) {
+            out.flush()?;
+            cpu.err = true;
+            cpu.errmsg = e.kind().to_string();
+            cpu.running = false;
+            eprintln!("{}", render_crash(&e));
+            print_crash_report(&cpu, &symbols, &e);
+            return Ok(false);
+        }
+        if args.registers {
+            writeln!(out, "    {}", register_line(&cpu))?;
+        }
+    }
+    out.flush()?;
+    Ok(true)
+}
+
+fn register_line(cpu: &CPU) -> String {
+    let mut line = String::new();
+    for (n, value) in cpu.int_reg.iter().enumerate() {
+        line.push_str(&format!("r{n}={value} "));
+    }
+    for (n, value) in cpu.float_reg.iter().enumerate() {
+        line.push_str(&format!("r{}={value} ", n + cpu.int_reg.len()));
+    }
+    let flag = |set: bool, name: char| if set { name } else { '-' };
+    line.push_str(&format!(
+        "sp=x{:04X} bp=x{:04X} {}{}{}{}",
+        cpu.sp,
+        cpu.bp,
+        flag(cpu.zflag, 'z'),
+        flag(cpu.oflag, 'o'),
+        flag(cpu.rflag, 'r'),
+        flag(cpu.sflag, 's')
+    ));
+    line
+}
+
+#[test]
+fn trace_registers_show_every_flag() {
+    let mut cpu = CPU::new();
+    cpu.rflag = true;
+    cpu.sflag = true;
+    assert!(register_line(&cpu).ends_with("sp=x0063 bp=x0063 --rs"));
+}
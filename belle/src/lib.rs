@@ -3,7 +3,19 @@ pub use hardware::*;
 pub mod config;
 pub mod debugger;
 pub use debugger::*;
+pub mod coverage;
+pub use coverage::*;
 pub mod crashdump;
 pub use crashdump::*;
+pub mod inspect;
+pub use inspect::*;
 pub mod misc;
 pub use misc::*;
+pub mod profiler;
+pub use profiler::*;
+pub mod render;
+pub use render::*;
+pub mod symbols;
+pub use symbols::*;
+pub mod test_runner;
+pub use test_runner::*;
@@ -4,33 +4,85 @@
  *
  * This code is licensed under the BSD 3-Clause License.
  */
-use crate::config::CONFIG;
+use crate::config::{print_config, Command, CONFIG};
 use belle::*;
 use std::{io, process};
 
 fn main() -> io::Result<()> {
+    if CONFIG.print_config {
+        print_config(&CONFIG);
+        return Ok(());
+    }
     cli_argument_check();
 
     let executable_path = &CONFIG.rom;
-    if CONFIG.debug {
-        let mut bdb = BDB::new(executable_path)?;
-        if let Err(e) = bdb.run() {
-            eprintln!("{e}");
-            process::exit(1);
+    match &CONFIG.command {
+        Command::Test { manifest, jobs } => {
+            if !run_test_manifest(manifest, *jobs) {
+                process::exit(1);
+            }
+            return Ok(());
+        }
+        Command::Info { rom, format } => {
+            if !print_info(rom, format.thin)? {
+                process::exit(1);
+            }
+            return Ok(());
+        }
+        Command::Disasm { rom, format } => {
+            if !print_disasm(rom, format.thin)? {
+                process::exit(1);
+            }
+            return Ok(());
+        }
+        Command::UpgradeRom {
+            rom,
+            output,
+            format,
+        } => {
+            if !upgrade_rom_file(rom, output.as_deref(), format.thin)? {
+                process::exit(1);
+            }
+            return Ok(());
+        }
+        Command::Trace(args) => {
+            if !run_trace(args)? {
+                process::exit(1);
+            }
+            return Ok(());
+        }
+        Command::Debug(args) => {
+            let mut bdb = match &args.core {
+                Some(core) => BDB::from_core(core)?,
+                None => BDB::new(executable_path)?,
+            };
+            // `tui = true` in belle.toml counts as well as --tui
+            let result = if CONFIG.tui { bdb.run_tui() } else { bdb.run() };
+            if let Err(e) = result {
+                eprintln!("{e}");
+                process::exit(1);
+            }
+            return Ok(());
         }
+        Command::Run(_) => {}
     }
     let rom = create_rom(executable_path)?;
     let mut cpu = CPU::new();
+    cpu.warning_handler = Some(print_warning);
     if let Err(e) = cpu.load_rom(&rom) {
-        eprintln!("{e}");
+        eprintln!("{}", render_emu_error(&e));
         process::exit(1);
     }
     if let Err(e) = cpu.run() {
         if !CONFIG.pretty && !CONFIG.compact_print {
-            eprintln!("{e}");
+            eprintln!("{}", render_crash(&e));
+            print_crash_report(&cpu, &SymbolTable::for_rom(executable_path), &e);
         }
         if CONFIG.write {
-            write_crash(&cpu);
+            match write_crash(&cpu, executable_path, Some(&e)) {
+                Ok(filename) => eprintln!("Crash dump written to {filename}"),
+                Err(e) => eprintln!("Failed to write crash dump: {e}"),
+            }
         }
         process::exit(1);
     }
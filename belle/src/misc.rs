@@ -1,6 +1,8 @@
-use crate::config::CONFIG;
+use crate::config::{Command, CONFIG};
+use crate::{render_emu_error, OLDEST_ROM_VERSION};
 use colored::Colorize;
 use std::{
+    collections::VecDeque,
     error::Error,
     fmt,
     fs::{self, File},
@@ -114,26 +116,31 @@ pub fn length_without_ansi(text: &str) -> usize {
     cleaned_text.len()
 }
 pub fn cli_argument_check() {
-    if CONFIG.debug && CONFIG.verbose {
-        eprintln!(
-            "{}",
-            EmuError::Impossible("Cannot have both debug and verbose flags".to_string())
-        );
-        process::exit(1);
+    // tests name their own ROMs, and clap has checked the rest
+    if matches!(CONFIG.command, Command::Test { .. }) || CONFIG.print_config {
+        return;
     }
-    if CONFIG.compact_print && !(CONFIG.debug || CONFIG.verbose) {
-        eprintln!(
-            "{}",
-            EmuError::Impossible(
-                "Must enable debug or verbose flags with compact print".to_string()
-            )
-        );
-        process::exit(1);
+    if let Some(path) = &CONFIG.input {
+        if let Err(e) = fs::metadata(path) {
+            eprintln!(
+                "[{}]: Cannot open input file {}: {e}",
+                "EMULATOR ERROR".red(),
+                path.green()
+            );
+            process::exit(1);
+        }
+    }
+    // a crash dump names its own ROM
+    if CONFIG.core.is_some() {
+        return;
     }
     let executable_path = &CONFIG.rom;
     if let Ok(metadata) = fs::metadata(executable_path) {
         if metadata.is_dir() {
-            eprintln!("{}", EmuError::IsDirectory());
+            eprintln!(
+                "{}",
+                render_emu_error(&EmuError::IsDirectory(executable_path.clone()))
+            );
             process::exit(1);
         }
     }
@@ -141,11 +148,33 @@ pub fn cli_argument_check() {
         if executable_path.is_empty() {
             process::exit(0);
         }
-        eprintln!("{}", EmuError::FileNotFound());
+        eprintln!(
+            "{}",
+            render_emu_error(&EmuError::FileNotFound(executable_path.clone()))
+        );
         process::exit(1);
     }
 }
 
+/// The bytes given with `--input` or `--input-string`, if any
+pub fn scripted_input() -> Option<VecDeque<u8>> {
+    if let Some(text) = &CONFIG.input_string {
+        return Some(text.bytes().collect());
+    }
+    let path = CONFIG.input.as_ref()?;
+    match fs::read(path) {
+        Ok(bytes) => Some(bytes.into()),
+        Err(e) => {
+            eprintln!(
+                "[{}]: Cannot read input file {}: {e}",
+                "EMULATOR ERROR".red(),
+                path.green()
+            );
+            Some(VecDeque::new())
+        }
+    }
+}
+
 pub fn create_rom(file_path: &str) -> io::Result<Vec<i16>> {
     let mut file = File::open(file_path)?;
     let mut buffer = Vec::new();
@@ -165,69 +194,76 @@ pub fn create_rom(file_path: &str) -> io::Result<Vec<i16>> {
 
 #[derive(Debug)]
 pub enum EmuError {
-    FileNotFound(),
-    IsDirectory(),
+    /// The path of a ROM that does not exist
+    FileNotFound(String),
+    /// The path of a ROM that is a directory
+    IsDirectory(String),
     MemoryOverflow(),
     Duplicate(String),
     ReadFail(String),
     Impossible(String),
+    /// The words of code the header gives, and how many the ROM has
+    RomLength(usize, usize),
+    /// The checksum the header gives, and the checksum of the ROM
+    RomChecksum(u16, u16),
+    /// The first and last address of two segments that fill the same memory
+    SegmentOverlap((u16, u16), (u16, u16)),
+    /// The path of a `--load` image that could not be read, and why
+    OverlayRead(String, String),
+    /// The first address the program arguments need, and the first and last
+    /// address of the segment already there
+    ArgsOverlap(u16, (u16, u16)),
+    NoRomHeader(),
+    /// A format version this emulator cannot read
+    RomVersion(u8),
 }
 
 impl fmt::Display for EmuError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            EmuError::FileNotFound() => {
-                if !CONFIG.rom.to_string().trim().is_empty() {
-                    write!(
-                        f,
-                        "{} File {} not found",
-                        "Emulator Error:".red(),
-                        CONFIG.rom.to_string().green(),
-                    )
-                } else {
-                    write!(
-                        f,
-                        "[{}]: {}",
-                        "EMULATOR ERROR".red(),
-                        "No ROM provided".bold()
-                    )
-                }
-            }
-            EmuError::MemoryOverflow() => {
-                write!(
-                    f,
-                    "[{}]: {}",
-                    "EMULATOR ERROR".red(),
-                    "Memory will overflow".red()
-                )
-            }
-            EmuError::Duplicate(s) => {
-                write!(f, "[{}]: Duplicate: {}", "EMULATOR ERROR".red(), s.red(),)
-            }
+            EmuError::FileNotFound(path) => write!(f, "File {path} not found"),
+            EmuError::IsDirectory(path) => write!(f, "{path} is a directory"),
+            EmuError::MemoryOverflow() => write!(f, "Memory will overflow"),
+            EmuError::Duplicate(s) => write!(f, "Duplicate: {s}"),
             EmuError::ReadFail(s) => {
-                write!(
-                    f,
-                    "[{}]: Failed to read from stdin and parse to i16: {}",
-                    "EMULATOR ERROR".red(),
-                    s,
-                )
+                write!(f, "Failed to read from stdin and parse to i16: {s}")
             }
             EmuError::Impossible(s) => {
-                write!(
-                    f,
-                    "[{}]: Configuration combination not possible: {}",
-                    "EMULATOR ERROR".red(),
-                    s,
-                )
+                write!(f, "Configuration combination not possible: {s}")
             }
-            EmuError::IsDirectory() => {
+            EmuError::RomLength(expected, found) => write!(
+                f,
+                "ROM should have {expected} words of code but has {found}, it may be truncated or corrupted"
+            ),
+            EmuError::RomChecksum(expected, found) => write!(
+                f,
+                "ROM has checksum {found:04X} but its header says {expected:04X}, it may be corrupted"
+            ),
+            EmuError::SegmentOverlap((first_start, first_end), (second_start, second_end)) => {
                 write!(
                     f,
-                    "[{}]: {} is a directory",
-                    "EMULATOR ERROR".red(),
-                    CONFIG.rom.to_string().green(),
+                    "Segment at x{second_start:04X}-x{second_end:04X} overlaps segment at x{first_start:04X}-x{first_end:04X}"
                 )
             }
+            EmuError::OverlayRead(path, e) => write!(f, "Cannot read {path} for --load: {e}"),
+            EmuError::ArgsOverlap(table, (start, end)) => write!(
+                f,
+                "Program arguments need x{table:04X}-xFFFF, which overlaps segment at x{start:04X}-x{end:04X}"
+            ),
+            EmuError::NoRomHeader() => write!(
+                f,
+                "ROM has no header, pass --thin <ADDR> to load it as raw code at an address"
+            ),
+            EmuError::RomVersion(version) if *version > basm::ROM_VERSION => write!(
+                f,
+                "ROM is format version {version}, but this emulator only reads up to version {}; update belle to run it",
+                basm::ROM_VERSION
+            ),
+            EmuError::RomVersion(version) => write!(
+                f,
+                "ROM is format version {version}, which is not a known version (versions {OLDEST_ROM_VERSION} to {} can be read)",
+                basm::ROM_VERSION
+            ),
         }
     }
 }
@@ -0,0 +1,256 @@
+use crate::*;
+use colored::Colorize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, Write},
+};
+
+const HOT_SPOTS_SHOWN: usize = 20;
+
+// one node of the call tree, keyed by the subroutine's entry address
+struct Frame {
+    parent: usize,
+    addr: u16,
+    cycles: u64,
+}
+
+/// Counts executions per address and per instruction kind, and attributes
+/// cycles to subroutines by following the return addresses that jumps push
+/// while pushret is set
+pub struct Profiler {
+    counts: Box<[u64; MEMORY_SIZE]>,
+    kinds: HashMap<&'static str, u64>,
+    frames: Vec<Frame>,
+    children: HashMap<(usize, u16), usize>,
+    calls: HashMap<u16, u64>,
+    current: usize,
+    // stack slot of each live frame's return address, and whether the stack grows upwards
+    return_slots: Vec<(u16, bool)>,
+    total: u64,
+}
+
+impl Profiler {
+    pub fn new(starts_at: u16) -> Self {
+        Self {
+            counts: Box::new([0; MEMORY_SIZE]),
+            kinds: HashMap::new(),
+            frames: vec![Frame {
+                parent: 0,
+                addr: starts_at,
+                cycles: 0,
+            }],
+            children: HashMap::new(),
+            calls: HashMap::new(),
+            current: 0,
+            return_slots: Vec::new(),
+            total: 0,
+        }
+    }
+
+    /// Records one executed instruction. `pc` and `pushret` are from before it ran.
+    pub fn record(&mut self, pc: u16, pushret: bool, ins: &Instruction, cpu: &CPU) {
+        self.total += 1;
+        self.counts[pc as usize] += 1;
+        *self.kinds.entry(ins.mnemonic()).or_insert(0) += 1;
+        self.frames[self.current].cycles += 1;
+
+        if pushret && ins.is_jump() && cpu.pc != pc.wrapping_add(1) {
+            let next = self.frames.len();
+            let parent = self.current;
+            let child = *self.children.entry((parent, cpu.pc)).or_insert(next);
+            if child == next {
+                self.frames.push(Frame {
+                    parent,
+                    addr: cpu.pc,
+                    cycles: 0,
+                });
+            }
+            *self.calls.entry(cpu.pc).or_insert(0) += 1;
+            self.current = child;
+            let backward = cpu.sp > cpu.bp || cpu.backward_stack;
+            self.return_slots.push((cpu.sp, backward));
+            return;
+        }
+
+        // a frame ends once its return address is off the stack, whether by RET or POP
+        let mut returned = false;
+        while let Some(&(slot, backward)) = self.return_slots.last() {
+            let popped = if backward {
+                cpu.sp < slot
+            } else {
+                cpu.sp > slot
+            };
+            if !popped {
+                break;
+            }
+            self.leave_frame();
+            returned = true;
+        }
+        if matches!(ins, Instruction::RET) && !returned {
+            self.leave_frame();
+        }
+    }
+
+    fn leave_frame(&mut self) {
+        if self.current != 0 {
+            self.return_slots.pop();
+            self.current = self.frames[self.current].parent;
+        }
+    }
+
+    pub fn report(&self, cpu: &CPU, symbols: &SymbolTable) {
+        let name = |addr: u16| match symbols.name_at(addr) {
+            Some(label) => format!("{label} (x{addr:04X})"),
+            None => format!("x{addr:04X}"),
+        };
+        let percent = |n: u64| n as f64 * 100.0 / self.total.max(1) as f64;
+
+        println!(
+            "[{}]: profiled {} instructions",
+            "EMULATOR INFO".yellow(),
+            self.total
+        );
+
+        println!("\n{}", "Hot spots".bold());
+        let mut hot: Vec<(usize, u64)> = self
+            .counts
+            .iter()
+            .enumerate()
+            .filter(|(_, &n)| n > 0)
+            .map(|(addr, &n)| (addr, n))
+            .collect();
+        hot.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        for (rank, (addr, n)) in hot.iter().take(HOT_SPOTS_SHOWN).enumerate() {
+            println!(
+                "{:>4}. {} {n:>10} {:>6.2}%  {}",
+                rank + 1,
+                format!("x{addr:04X}").magenta(),
+                percent(*n),
+                CPU::decode_word(cpu.memory[*addr]).to_string().green()
+            );
+        }
+
+        println!("\n{}", "Instructions".bold());
+        let mut kinds: Vec<(&&str, &u64)> = self.kinds.iter().collect();
+        kinds.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (kind, n) in kinds {
+            println!("{:>6} {n:>10} {:>6.2}%", kind.green(), percent(*n));
+        }
+
+        println!("\n{}", "Subroutines".bold());
+        println!(
+            "{:<28} {:>8} {:>10} {:>10} {:>8}",
+            "name", "calls", "self", "total", "total %"
+        );
+        let mut subroutines: Vec<(u16, u64, u64)> = self
+            .subroutine_cycles()
+            .into_iter()
+            .map(|(addr, (own, total))| (addr, own, total))
+            .collect();
+        subroutines.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+        for (addr, own, total) in subroutines {
+            println!(
+                "{:<28} {:>8} {own:>10} {total:>10} {:>7.2}%",
+                name(addr),
+                self.calls.get(&addr).copied().unwrap_or(0),
+                percent(total)
+            );
+        }
+    }
+
+    // self and inclusive cycles per subroutine; recursive frames are only counted once
+    fn subroutine_cycles(&self) -> BTreeMap<u16, (u64, u64)> {
+        let mut cycles: BTreeMap<u16, (u64, u64)> = BTreeMap::new();
+        for (index, frame) in self.frames.iter().enumerate() {
+            cycles.entry(frame.addr).or_default().0 += frame.cycles;
+            let mut seen = Vec::new();
+            for addr in self.stack(index) {
+                if !seen.contains(&addr) {
+                    seen.push(addr);
+                    cycles.entry(addr).or_default().1 += frame.cycles;
+                }
+            }
+        }
+        cycles
+    }
+
+    // entry addresses from the outermost frame down to `index`
+    fn stack(&self, mut index: usize) -> Vec<u16> {
+        let mut stack = vec![self.frames[index].addr];
+        while index != 0 {
+            index = self.frames[index].parent;
+            stack.push(self.frames[index].addr);
+        }
+        stack.reverse();
+        stack
+    }
+
+    /// Writes one `outer;inner count` line per call stack, as read by flamegraph tools
+    pub fn write_collapsed(&self, out: &mut impl Write, symbols: &SymbolTable) -> io::Result<()> {
+        let name = |addr: u16| match symbols.name_at(addr) {
+            Some(label) => label.to_string(),
+            None => format!("x{addr:04X}"),
+        };
+        for (index, frame) in self.frames.iter().enumerate() {
+            if frame.cycles == 0 {
+                continue;
+            }
+            let stack: Vec<String> = self.stack(index).into_iter().map(name).collect();
+            writeln!(out, "{} {}", stack.join(";"), frame.cycles)?;
+        }
+        Ok(())
+    }
+
+    /// Prints the report with the labels of `rom`'s symbol file and writes the collapsed stacks to `path`
+    pub fn finish(&self, cpu: &CPU, rom: &str, path: &str) {
+        let symbols = SymbolTable::for_rom(rom);
+        self.report(cpu, &symbols);
+        let written =
+            File::create(path).and_then(|mut file| self.write_collapsed(&mut file, &symbols));
+        match written {
+            Ok(()) => println!("\nWrote collapsed stacks to {path}"),
+            Err(e) => eprintln!("Failed to write collapsed stacks to {path}: {e}"),
+        }
+    }
+}
+
+#[test]
+fn collapsed_stacks_follow_calls() {
+    let symbols = SymbolTable::parse("label main 0x0100\nlabel f 0x0200\nlabel g 0x0300\n");
+    let mut cpu = CPU::new();
+    let mut profiler = Profiler::new(0x100);
+    let add = Instruction::ADD(Argument::Register(0), Argument::Literal(1));
+    let call = Instruction::JMP(Argument::Literal(0));
+    // (pc before, instruction, pc after, sp after)
+    let trace = [
+        (0x100, &add, 0x101, 99),
+        (0x101, &call, 0x200, 98),
+        (0x200, &add, 0x201, 98),
+        (0x201, &call, 0x300, 97),
+        (0x300, &add, 0x301, 97),
+        (0x301, &Instruction::RET, 0x202, 98),
+        (0x202, &Instruction::RET, 0x102, 99),
+        // nothing left to return from, so this stays in main
+        (0x102, &Instruction::RET, 0x103, 99),
+        (0x103, &Instruction::HLT, 0x103, 99),
+    ];
+    for (pc, ins, next, sp) in trace {
+        cpu.pc = next;
+        cpu.sp = sp;
+        profiler.record(pc, true, ins, &cpu);
+    }
+
+    let mut out = Vec::new();
+    profiler.write_collapsed(&mut out, &symbols).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "main 4\nmain;f 3\nmain;f;g 2\n"
+    );
+    let cycles = profiler.subroutine_cycles();
+    assert_eq!(cycles[&0x100], (4, 9));
+    assert_eq!(cycles[&0x200], (3, 5));
+    assert_eq!(cycles[&0x300], (2, 2));
+    assert_eq!(profiler.calls[&0x200], 1);
+    assert_eq!(profiler.total, 9);
+}
@@ -0,0 +1,79 @@
+use crate::config::CONFIG;
+use crate::*;
+use colored::*;
+
+/// The one-line form of a crash, as shown in the CPU state
+pub fn crash_summary(kind: &str) -> String {
+    format!("{} {}", "UNRECOVERABLE ERROR:".red(), kind.bold().red())
+}
+
+/// A crash as the command line shows it. Debug and verbose runs also get the
+/// message and a box with the instruction and its address.
+pub fn render_crash(error: &UnrecoverableError) -> String {
+    let mut text = crash_summary(error.kind());
+    if !(CONFIG.debug || CONFIG.verbose) {
+        return text;
+    }
+    if let Some(message) = error.message() {
+        text.push_str(&format!("\n{}", message.yellow()));
+    }
+    let line = "─".repeat(12);
+    let instruction = CPU::decode_word(error.ir() as u16).to_string();
+    let location = error.location().to_string();
+    let padding = 52usize.saturating_sub(
+        "│ Instruction".len() + instruction.trim().len() + " Address: ".len() + location.len(),
+    );
+    text.push_str(&format!("\n╭{line}──────────{line}─{line}────╮\n"));
+    text.push_str(&format!(
+        "│{}: {} {}: {}{}│\n",
+        " Instruction".bold(),
+        instruction.bold(),
+        "Address".blue().bold(),
+        location.bold(),
+        " ".repeat(padding)
+    ));
+    text.push_str(&format!("╰{line}──────────{line}─{line}────╯\n"));
+    text
+}
+
+/// A recoverable error as the command line shows it, if it should be shown at
+/// all. Warnings from the checks are always shown; the rest only when
+/// debugging or verbose, and not with a compact print.
+pub fn render_warning(warning: &RecoverableError) -> Option<String> {
+    if warning.is_check() {
+        return Some(format!(
+            "{} at x{:04X}: {}",
+            "WARNING".yellow(),
+            warning.location(),
+            warning.message().unwrap_or(warning.kind())
+        ));
+    }
+    if CONFIG.compact_print || !(CONFIG.debug || CONFIG.verbose) {
+        return None;
+    }
+    let mut text = format!(
+        "{}: {}",
+        "RECOVERABLE ERROR".yellow(),
+        warning.kind().yellow()
+    );
+    if let Some(message) = warning.message() {
+        text.push_str(&format!(": {}", message.magenta()));
+    }
+    text.push_str(&format!(
+        " at memory address {}",
+        warning.location().to_string().green()
+    ));
+    Some(text)
+}
+
+/// The warning handler the command line gives its CPUs
+pub fn print_warning(warning: &RecoverableError) {
+    if let Some(text) = render_warning(warning) {
+        eprintln!("{text}");
+    }
+}
+
+/// An error from loading or configuring the emulator as the command line shows it
+pub fn render_emu_error(error: &EmuError) -> String {
+    format!("[{}]: {error}", "EMULATOR ERROR".red())
+}
@@ -0,0 +1,120 @@
+use std::{collections::BTreeMap, fmt, fs, io, path::Path};
+
+/// Labels and instruction source lines written by `basm --symbols` into `<binary>.sym`
+#[derive(Debug, Default, Clone)]
+pub struct SymbolTable {
+    pub labels: BTreeMap<String, u16>,
+    pub lines: BTreeMap<u16, SourceLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLine {
+    pub file: String,
+    pub line: usize,
+}
+
+impl fmt::Display for SourceLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+impl SymbolTable {
+    /// Loads the symbol file next to a ROM, if there is one
+    pub fn for_rom(rom_path: &str) -> Self {
+        let path = format!("{rom_path}.sym");
+        if Path::new(&path).exists() {
+            Self::load(&path).unwrap_or_default()
+        } else {
+            Self::default()
+        }
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut table = Self::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            // file names come last as they may contain spaces
+            let fields: Vec<&str> = line.splitn(4, char::is_whitespace).collect();
+            match fields.as_slice() {
+                ["label", name, addr] => {
+                    if let Some(addr) = parse_address(addr) {
+                        table.labels.insert(name.to_string(), addr);
+                    }
+                }
+                ["line", addr, number, file] => {
+                    if let (Some(addr), Ok(line)) = (parse_address(addr), number.parse()) {
+                        let file = file.to_string();
+                        table.lines.insert(addr, SourceLine { file, line });
+                    }
+                }
+                _ => {}
+            }
+        }
+        table
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<u16> {
+        self.labels.get(name).copied()
+    }
+
+    /// The label at an address, if there is one
+    pub fn name_at(&self, addr: u16) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|(_, &a)| a == addr)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// `label+offset` from the closest label at or before an address
+    pub fn symbolize(&self, addr: u16) -> Option<String> {
+        let (name, start) = self
+            .labels
+            .iter()
+            .filter(|(_, &a)| a <= addr)
+            .max_by_key(|(_, &a)| a)?;
+        Some(match addr - start {
+            0 => name.clone(),
+            offset => format!("{name}+{offset}"),
+        })
+    }
+
+    pub fn source(&self, addr: u16) -> Option<&SourceLine> {
+        self.lines.get(&addr)
+    }
+
+    /// An address with whatever the table knows about it, like `x0105 <loop+2> (main.asm:20)`
+    pub fn describe(&self, addr: u16) -> String {
+        describe_address(
+            addr,
+            self.symbolize(addr).as_deref(),
+            self.source(addr).map(|s| s.to_string()).as_deref(),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty() && self.lines.is_empty()
+    }
+}
+
+pub fn describe_address(addr: u16, symbol: Option<&str>, source: Option<&str>) -> String {
+    let mut text = format!("x{addr:04X}");
+    if let Some(symbol) = symbol {
+        text.push_str(&format!(" <{symbol}>"));
+    }
+    if let Some(source) = source {
+        text.push_str(&format!(" ({source})"));
+    }
+    text
+}
+
+fn parse_address(text: &str) -> Option<u16> {
+    u16::from_str_radix(text.trim_start_matches("0x"), 16).ok()
+}
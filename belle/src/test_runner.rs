@@ -0,0 +1,349 @@
+use crate::bdb_utils::parse_number;
+use crate::*;
+use colored::Colorize;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+pub const DEFAULT_CYCLE_LIMIT: u64 = 1_000_000;
+
+/// The `[[test]]` tables of a test manifest
+#[derive(Debug, Deserialize)]
+pub struct TestManifest {
+    #[serde(rename = "test", default)]
+    pub tests: Vec<TestCase>,
+}
+
+/// One program to run and what it should do. Paths are relative to the manifest.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestCase {
+    pub name: Option<String>,
+    /// A ROM, or an `.asm` file that is assembled with `basm` first
+    pub rom: String,
+    /// Arguments placed in memory as if given after `--`
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub input: Option<String>,
+    pub input_file: Option<String>,
+    #[serde(default = "default_cycles")]
+    pub cycles: u64,
+    pub stdout: Option<String>,
+    pub stdout_file: Option<String>,
+    /// `r0` to `r7`, `pc`, `sp` and `bp`
+    #[serde(default)]
+    pub registers: BTreeMap<String, f64>,
+    /// Addresses, in decimal, hex or binary, and the words expected there
+    #[serde(default)]
+    pub memory: BTreeMap<String, u16>,
+    /// The kind of crash expected, like "Divide by zero"
+    pub crash: Option<String>,
+}
+
+fn default_cycles() -> u64 {
+    DEFAULT_CYCLE_LIMIT
+}
+
+impl TestCase {
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.rom)
+    }
+}
+
+impl TestManifest {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("cannot read {path}: {e}"))?;
+        toml::from_str(&text).map_err(|e| format!("invalid manifest {path}: {e}"))
+    }
+}
+
+/// Runs every test in a manifest, printing a summary. Returns whether they all passed.
+pub fn run_test_manifest(path: &str, jobs: Option<usize>) -> bool {
+    let manifest = match TestManifest::load(path) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("[{}]: {e}", "EMULATOR ERROR".red());
+            return false;
+        }
+    };
+    let base = Path::new(path).parent().unwrap_or(Path::new("."));
+    let tests = &manifest.tests;
+    let jobs = jobs
+        .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1)
+        .clamp(1, tests.len().max(1));
+
+    // failures of each test, in manifest order
+    let results: Mutex<Vec<Option<Vec<String>>>> = Mutex::new(vec![None; tests.len()]);
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(case) = tests.get(index) else {
+                    break;
+                };
+                let failures = run_test_case(case, base, index);
+                results.lock().unwrap()[index] = Some(failures);
+            });
+        }
+    });
+
+    let results = results.into_inner().unwrap();
+    let mut failed = Vec::new();
+    for (case, failures) in tests.iter().zip(results) {
+        let failures = failures.unwrap_or_default();
+        if failures.is_empty() {
+            println!("{} {}", "PASS".green().bold(), case.name());
+        } else {
+            println!("{} {}", "FAIL".red().bold(), case.name());
+            for failure in &failures {
+                println!("    {}", failure.replace('\n', "\n    "));
+            }
+            failed.push(case.name());
+        }
+    }
+    println!(
+        "\n{} passed, {} failed, {} total",
+        (tests.len() - failed.len()).to_string().green(),
+        failed.len().to_string().red(),
+        tests.len()
+    );
+    failed.is_empty()
+}
+
+/// Runs one test headless, returning a description of each way it failed
+pub fn run_test_case(case: &TestCase, base: &Path, index: usize) -> Vec<String> {
+    let resolve = |file: &str| base.join(file);
+    let rom = match load_test_rom(&resolve(&case.rom), index) {
+        Ok(rom) => rom,
+        Err(e) => return vec![e],
+    };
+    let input = match (&case.input, &case.input_file) {
+        (Some(text), _) => Some(text.as_bytes().to_vec()),
+        (None, Some(file)) => match fs::read(resolve(file)) {
+            Ok(bytes) => Some(bytes),
+            Err(e) => return vec![format!("cannot read input file {file}: {e}")],
+        },
+        (None, None) => None,
+    };
+    let expected_stdout = match (&case.stdout, &case.stdout_file) {
+        (Some(text), _) => Some(text.clone()),
+        (None, Some(file)) => match fs::read_to_string(resolve(file)) {
+            Ok(text) => Some(text),
+            Err(e) => return vec![format!("cannot read stdout file {file}: {e}")],
+        },
+        (None, None) => None,
+    };
+
+    // only what the case gives applies, not the flags `belle test` was run with
+    let options = LoadOptions {
+        args: case.args.clone(),
+        ..LoadOptions::default()
+    };
+    let mut cpu = CPU::new();
+    if let Err(e) = cpu.load_rom_with(&rom, &options) {
+        return vec![e.to_string()];
+    }
+    // an empty input still means the program must not wait on the terminal
+    cpu.input = Some(input.unwrap_or_default().into());
+    cpu.output = Some(String::new());
+
+    let mut failures = Vec::new();
+    let (result, cycles) = run_headless(&mut cpu, case.cycles);
+    if cpu.running {
+        failures.push(format!(
+            "did not halt within {} cycles",
+            case.cycles.to_string().yellow()
+        ));
+    }
+    match (&result, &case.crash) {
+        (Ok(()), Some(expected)) => {
+            failures.push(format!("expected to crash with {expected}, but it did not"))
+        }
+        (Err(e), None) => failures.push(format!(
+            "crashed with {} at x{:04X} after {cycles} cycles",
+            e.kind().red(),
+            e.location()
+        )),
+        (Err(e), Some(expected)) if !e.kind().eq_ignore_ascii_case(expected) => {
+            failures.push(format!(
+                "expected to crash with {expected}, but crashed with {}",
+                e.kind().red()
+            ))
+        }
+        _ => {}
+    }
+
+    let output = cpu.output.take().unwrap_or_default();
+    if let Some(expected) = expected_stdout {
+        if output != expected {
+            failures.push(format!(
+                "stdout differs:\n{}",
+                diff_lines(&expected, &output)
+            ));
+        }
+    }
+    for (register, &expected) in &case.registers {
+        match register_value(&cpu, register) {
+            Some(actual) if (actual - expected).abs() < 1e-4 => {}
+            Some(actual) => failures.push(format!(
+                "{register} is {}, expected {}",
+                actual.to_string().red(),
+                expected.to_string().green()
+            )),
+            None => failures.push(format!("unknown register '{register}'")),
+        }
+    }
+    for (addr, &expected) in &case.memory {
+        let Ok(index) = parse_number::<u16>(addr) else {
+            failures.push(format!("invalid memory address '{addr}'"));
+            continue;
+        };
+        let actual = cpu.memory[index as usize];
+        if actual != expected {
+            failures.push(format!(
+                "x{index:04X} is {}, expected {}",
+                actual.to_string().red(),
+                expected.to_string().green()
+            ));
+        }
+    }
+    failures
+}
+
+// steps until the CPU halts, crashes or runs out of cycles
+fn run_headless(cpu: &mut CPU, limit: u64) -> (PossibleCrash, u64) {
+    cpu.running = true;
+    let mut cycles = 0;
+    while cpu.running && cycles < limit {
+        cycles += 1;
+        cpu.ir = cpu.memory[cpu.pc as usize] as i16;
+        let parsed_ins = cpu.decode_instruction();
+        if let Err(e) = cpu.execute_instruction(&parsed_ins) {
+            cpu.err = true;
+            cpu.errmsg = e.kind().to_string();
+            cpu.running = false;
+            return (Err(e), cycles);
+        }
+    }
+    (Ok(()), cycles)
+}
+
+fn register_value(cpu: &CPU, name: &str) -> Option<f64> {
+    match name {
+        "pc" => Some(cpu.pc.into()),
+        "sp" => Some(cpu.sp.into()),
+        "bp" => Some(cpu.bp.into()),
+        "r6" => Some(cpu.float_reg[0].into()),
+        "r7" => Some(cpu.float_reg[1].into()),
+        _ => {
+            let n: usize = name.strip_prefix('r')?.parse().ok()?;
+            cpu.int_reg.get(n).map(|&v| v.into())
+        }
+    }
+}
+
+fn load_test_rom(path: &Path, index: usize) -> Result<Vec<i16>, String> {
+    if path.extension().is_some_and(|ext| ext == "asm") {
+        let out = env::temp_dir().join(format!("belle-test-{}-{index}.bin", process::id()));
+        assemble_with_basm(path, &out)?;
+        let rom = create_rom(&out.to_string_lossy());
+        let _ = fs::remove_file(&out);
+        return rom.map_err(|e| format!("cannot read assembled {}: {e}", path.display()));
+    }
+    create_rom(&path.to_string_lossy()).map_err(|e| format!("cannot read {}: {e}", path.display()))
+}
+
+// basm resolves includes against the working directory, so it runs next to the source
+fn assemble_with_basm(source: &Path, out: &Path) -> Result<(), String> {
+    let dir = source.parent().filter(|d| !d.as_os_str().is_empty());
+    let file = source.file_name().unwrap_or(source.as_os_str());
+    let mut command = process::Command::new(basm_path());
+    command.arg(file).arg("-o").arg(out);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = command
+        .output()
+        .map_err(|e| format!("cannot run basm: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "basm failed to assemble {}:\n{}{}",
+            source.display(),
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(())
+}
+
+// prefer the basm installed alongside this binary
+fn basm_path() -> PathBuf {
+    let name = format!("basm{}", env::consts::EXE_SUFFIX);
+    env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(&name)))
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+/// Marks the lines that differ between the expected and actual output
+pub fn diff_lines(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.split('\n').collect();
+    let actual: Vec<&str> = actual.split('\n').collect();
+    let mut diff = Vec::new();
+    for line in 0..expected.len().max(actual.len()) {
+        match (expected.get(line), actual.get(line)) {
+            (Some(e), Some(a)) if e == a => {}
+            (e, a) => {
+                if let Some(e) = e {
+                    diff.push(format!("{}", format!("-{:>4} | {e}", line + 1).red()));
+                }
+                if let Some(a) = a {
+                    diff.push(format!("{}", format!("+{:>4} | {a}", line + 1).green()));
+                }
+            }
+        }
+    }
+    diff.join("\n")
+}
+
+#[test]
+fn run_manifest_case() {
+    let words = assemble("add r0, 2\nint 0\nhlt").unwrap();
+    let words: Vec<i16> = words.into_iter().map(|word| word as i16).collect();
+    let segment = basm::Segment {
+        address: 0x100,
+        words,
+    };
+    let bytes = basm::build_rom(0x100, "", &[segment]);
+    let dir = env::temp_dir();
+    let rom = format!("belle-runner-{}.bin", process::id());
+    fs::write(dir.join(&rom), bytes).unwrap();
+
+    let manifest: TestManifest = toml::from_str(&format!(
+        "[[test]]\nrom = \"{rom}\"\nstdout = \"2\\n\"\nregisters = {{ r0 = 2 }}\n\
+         [[test]]\nrom = \"{rom}\"\nstdout = \"3\\n\"\ncrash = \"Divide by zero\"\n\
+         [[test]]\nrom = \"{rom}\"\nargs = [\"a\", \"b\"]\nregisters = {{ r0 = 4, r1 = 65530 }}\n"
+    ))
+    .unwrap();
+    let passing = run_test_case(&manifest.tests[0], &dir, 0);
+    let failing = run_test_case(&manifest.tests[1], &dir, 1);
+    // r0 starts at the number of arguments and r1 at their table
+    let with_args = run_test_case(&manifest.tests[2], &dir, 2);
+    let _ = fs::remove_file(dir.join(&rom));
+
+    assert!(passing.is_empty(), "{passing:?}");
+    assert_eq!(failing.len(), 2);
+    assert!(with_args.is_empty(), "{with_args:?}");
+}